@@ -1,14 +1,18 @@
 use anyhow::{anyhow, Result};
 use gimli::{
-    AttributeValue, EndianRcSlice, LittleEndian, Reader, Unit, UnitHeader, UnitOffset,
-    UnitSectionOffset,
+    AttributeValue, DebugAddrBase, DebugStrOffsetsBase, EndianRcSlice, Reader, RunTimeEndian,
+    Section, Unit, UnitHeader, UnitOffset, UnitSectionOffset,
 };
-use num_bigint::BigUint;
+use num_bigint::BigInt;
+use serde::Serialize;
+use std::cell::RefCell;
 use std::collections::HashMap;
+use std::io::Read;
 use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 use wasmparser::{Parser, Payload};
 
+pub mod pretty_printer;
 pub mod sourcemap;
 pub mod subroutine;
 pub mod utils;
@@ -16,24 +20,53 @@ pub mod variables;
 pub mod wasm_bindings;
 
 mod format;
+#[cfg(test)]
+pub(crate) mod test_utils;
 
 use crate::console_log;
-use format::format_object;
-use sourcemap::{transform_debug_line, DwarfSourceMap};
-use subroutine::{transform_subprogram, DwarfSubroutineMap};
+use format::{
+    format_json, format_object, format_text, format_variant, integer_value, unsigned_from_bytes,
+    DisplayRadix, FixedScale,
+};
+use pretty_printer::{MemberLayout, PrettyLayout};
+use sourcemap::{transform_debug_line, DwarfSourceMap, DwarfUnitSourceMap};
+use subroutine::{
+    index_subroutine_ranges, transform_subprogram, DwarfSubroutineMap, Subroutine,
+    DEFAULT_RBP_LOCAL_OFFSET,
+};
 use utils::{clone_string_attribute, error};
-use variables::{DwarfGlobalVariables, VariableLocation};
+use variables::{
+    evaluate_member_location, DwarfGlobalVariables, LazyGroups, MemberAddress, VariableLocation,
+    VariablePiece,
+};
+use wasm_bindings::MemorySliceVector;
 
-/// Dwarf reader definitions for wasm-dwarf-alanyser
-pub type DwarfReader = EndianRcSlice<LittleEndian>;
+/// Dwarf reader definitions for wasm-dwarf-alanyser. The byte order is carried by the
+/// reader, values are decoded in the order of the sections they were read from
+pub type DwarfReader = EndianRcSlice<RunTimeEndian>;
 pub type DwarfReaderOffset = <DwarfReader as Reader>::Offset;
 pub type Dwarf = gimli::Dwarf<DwarfReader>;
 pub type DwarfUnit = gimli::Unit<DwarfReader>;
 
+/// WebAssembly is little endian, and so are the DWARF sections embedded in it
+const SECTION_BYTE_ORDER: RunTimeEndian = RunTimeEndian::Little;
+
 /// Dwarf debug data utility
 #[derive(Clone)]
 pub struct DwarfDebugData {
     program_raw_data: HashMap<String, Rc<[u8]>>,
+    /// Sections of the split DWARF file (.dwo / .dwp)
+    supplementary_raw_data: HashMap<String, Rc<[u8]>>,
+    cache: Rc<RefCell<DwarfCache>>,
+}
+
+/// Parsed dwarf shared by every clone of DwarfDebugData
+#[derive(Default)]
+struct DwarfCache {
+    dwarf: Option<Rc<Dwarf>>,
+    dwo: Option<Rc<Dwarf>>,
+    headers: HashMap<UnitSectionOffset, UnitHeader<DwarfReader>>,
+    units: HashMap<UnitSectionOffset, (Rc<Dwarf>, Rc<DwarfUnit>)>,
 }
 
 impl DwarfDebugData {
@@ -45,7 +78,12 @@ impl DwarfDebugData {
         for payload in parser.parse_all(wasm_binary) {
             match payload? {
                 Payload::CustomSection { name, data, .. } => {
-                    sections.insert(String::from(name), Rc::from(data));
+                    // .zdebug_* is the GNU naming of compressed .debug_* sections
+                    let name = match name.strip_prefix(".zdebug_") {
+                        Some(rest) => format!(".debug_{}", rest),
+                        None => String::from(name),
+                    };
+                    sections.insert(name, Rc::from(decompress_section(data)?));
                 }
                 _ => continue,
             }
@@ -53,9 +91,21 @@ impl DwarfDebugData {
 
         Ok(Self {
             program_raw_data: sections,
+            supplementary_raw_data: HashMap::new(),
+            cache: Rc::new(RefCell::new(DwarfCache::default())),
         })
     }
 
+    /// Add a section of the split DWARF file, e.g. ".debug_info.dwo"
+    pub fn add_supplementary_sections(&mut self, name: &str, data: &[u8]) -> Result<()> {
+        self.supplementary_raw_data
+            .insert(String::from(name), Rc::from(decompress_section(data)?));
+        self.cache = Rc::new(RefCell::new(DwarfCache::default()));
+        Ok(())
+    }
+
+    /// Sections are looked up by SectionId::name, which covers the DWARF 5 sections
+    /// (.debug_str_offsets, .debug_addr, .debug_line_str, .debug_rnglists, .debug_loclists)
     pub fn parse_dwarf(&self) -> Result<Dwarf> {
         let load_section = |id: gimli::SectionId| -> Result<DwarfReader> {
             let data = match self.program_raw_data.get(id.name()) {
@@ -63,84 +113,415 @@ impl DwarfDebugData {
                 None => Rc::from(&[][..]),
             };
 
-            Ok(EndianRcSlice::new(data, LittleEndian))
+            Ok(EndianRcSlice::new(data, SECTION_BYTE_ORDER))
         };
 
         Dwarf::load(&load_section)
     }
 
-    pub fn unit_offset(&self, offset: UnitSectionOffset) -> Result<Option<(Dwarf, DwarfUnit)>> {
-        let dwarf = self.parse_dwarf()?;
-        let header = match header_from_offset(&dwarf, offset)? {
-            Some(header) => header,
+    /// Parse the split DWARF sections, or None when no supplementary data is provided
+    pub fn parse_dwo(&self, parent: &Dwarf) -> Result<Option<Dwarf>> {
+        if self.supplementary_raw_data.is_empty() {
+            return Ok(None);
+        }
+
+        let load_section = |id: gimli::SectionId| -> Result<DwarfReader> {
+            let data = match id
+                .dwo_name()
+                .and_then(|name| self.supplementary_raw_data.get(name))
+                .or_else(|| self.supplementary_raw_data.get(id.name()))
+            {
+                Some(section) => section.clone(),
+                None => Rc::from(&[][..]),
+            };
+
+            Ok(EndianRcSlice::new(data, SECTION_BYTE_ORDER))
+        };
+
+        let mut dwo = Dwarf::load(&load_section)?;
+        dwo.file_type = gimli::DwarfFileType::Dwo;
+        dwo.debug_addr = parent.debug_addr.clone();
+        dwo.ranges = parent.ranges.clone();
+        Ok(Some(dwo))
+    }
+
+    /// Parse dwarf once and index its unit headers by offset
+    fn cached_dwarf(&self) -> Result<Rc<Dwarf>> {
+        if let Some(ref dwarf) = self.cache.borrow().dwarf {
+            return Ok(dwarf.clone());
+        }
+
+        let dwarf = Rc::new(self.parse_dwarf()?);
+        let dwo = self.parse_dwo(&dwarf)?.map(Rc::new);
+        let mut headers = HashMap::new();
+        let mut units = dwarf.units();
+        while let Some(header) = units.next()? {
+            headers.insert(header.offset(), header);
+        }
+
+        let mut cache = self.cache.borrow_mut();
+        cache.dwarf = Some(dwarf.clone());
+        cache.dwo = dwo;
+        cache.headers = headers;
+        Ok(dwarf)
+    }
+
+    /// Offsets of every unit in the file, in section order
+    pub fn unit_offsets(&self) -> Result<Vec<UnitSectionOffset>> {
+        self.cached_dwarf()?;
+        let mut offsets: Vec<UnitSectionOffset> =
+            self.cache.borrow().headers.keys().copied().collect();
+        offsets.sort();
+        Ok(offsets)
+    }
+
+    pub fn unit_offset(
+        &self,
+        offset: UnitSectionOffset,
+    ) -> Result<Option<(Rc<Dwarf>, Rc<DwarfUnit>)>> {
+        let dwarf = self.cached_dwarf()?;
+        if let Some((dwarf, unit)) = self.cache.borrow().units.get(&offset) {
+            return Ok(Some((dwarf.clone(), unit.clone())));
+        }
+
+        let header = match self.cache.borrow().headers.get(&offset) {
+            Some(header) => header.clone(),
             None => {
                 return Ok(None);
             }
         };
 
-        let unit = dwarf.unit(header)?;
+        let unit = parse_unit(&dwarf, header)?;
+        let dwo = self.cache.borrow().dwo.clone();
+        let (dwarf, unit) = match dwo {
+            Some(dwo) => match split_unit(&dwo, &unit)? {
+                Some(split) => (dwo, Rc::new(split)),
+                None => (dwarf, Rc::new(unit)),
+            },
+            None => (dwarf, Rc::new(unit)),
+        };
+
+        self.cache
+            .borrow_mut()
+            .units
+            .insert(offset, (dwarf.clone(), unit.clone()));
         Ok(Some((dwarf, unit)))
     }
 }
 
+impl DwarfDebugData {
+    /// Indented text of the tags and attributes of the unit_index-th unit, for diagnosing
+    /// the shape of the debug info. None when there is no such unit
+    pub fn dump_die_tree(&self, unit_index: usize) -> Result<Option<String>> {
+        let offset = match self.unit_offsets()?.get(unit_index) {
+            Some(offset) => *offset,
+            None => return Ok(None),
+        };
+        let (dwarf, unit) = match self.unit_offset(offset)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+
+        let mut tree = unit.entries_tree(None)?;
+        let mut out = String::new();
+        dump_die_node(tree.root()?, &dwarf, &unit, 0, &mut out)?;
+        Ok(Some(out))
+    }
+}
+
+fn dump_die_node(
+    node: gimli::EntriesTreeNode<DwarfReader>,
+    dwarf: &Dwarf,
+    unit: &DwarfUnit,
+    depth: usize,
+    out: &mut String,
+) -> Result<()> {
+    let indent = "  ".repeat(depth);
+    let entry = node.entry();
+    out.push_str(&format!(
+        "{}<0x{:x}> {}\n",
+        indent,
+        entry.offset().0,
+        entry.tag()
+    ));
+
+    let mut attrs = entry.attrs();
+    while let Some(attr) = attrs.next()? {
+        let value = match attr.value() {
+            AttributeValue::UnitRef(offset) => format!("<0x{:x}>", offset.0),
+            value @ AttributeValue::String(_)
+            | value @ AttributeValue::DebugStrRef(_)
+            | value @ AttributeValue::DebugStrOffsetsIndex(_)
+            | value @ AttributeValue::DebugLineStrRef(_) => {
+                match clone_string_attribute(dwarf, unit, value.clone()) {
+                    Ok(string) => format!("{:?}", string),
+                    Err(_) => format!("{:?}", value),
+                }
+            }
+            value => format!("{:?}", value),
+        };
+        out.push_str(&format!("{}    {}: {}\n", indent, attr.name(), value));
+    }
+
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        dump_die_node(child, dwarf, unit, depth + 1, out)?;
+    }
+    Ok(())
+}
+
+/// Parse the unit, basing DWARF 5 indexed strings and addresses on the first contribution
+/// of .debug_str_offsets / .debug_addr when the unit has no DW_AT_str_offsets_base /
+/// DW_AT_addr_base. A base of 0 would point at the header of the contribution
+fn parse_unit(dwarf: &Dwarf, header: UnitHeader<DwarfReader>) -> Result<DwarfUnit> {
+    let mut unit = dwarf.unit(header)?;
+    if unit.header.version() < 5 || dwarf.file_type != gimli::DwarfFileType::Main {
+        return Ok(unit);
+    }
+
+    let header_size = match unit.header.format() {
+        gimli::Format::Dwarf32 => 8,
+        gimli::Format::Dwarf64 => 16,
+    };
+    if unit.str_offsets_base.0 == 0 && !dwarf.debug_str_offsets.reader().is_empty() {
+        unit.str_offsets_base = DebugStrOffsetsBase(header_size);
+    }
+    if unit.addr_base.0 == 0 && !dwarf.debug_addr.reader().is_empty() {
+        unit.addr_base = DebugAddrBase(header_size);
+    }
+    Ok(unit)
+}
+
+/// Find the split unit of the skeleton unit in the dwo file
+fn split_unit(dwo: &Dwarf, skeleton: &DwarfUnit) -> Result<Option<DwarfUnit>> {
+    let dwo_id = match skeleton.dwo_id {
+        Some(dwo_id) => dwo_id,
+        None => return Ok(None),
+    };
+
+    let mut headers = dwo.units();
+    while let Some(header) = headers.next()? {
+        let mut unit = dwo.unit(header)?;
+        if unit.dwo_id == Some(dwo_id) {
+            unit.copy_relocated_attributes(skeleton);
+            return Ok(Some(unit));
+        }
+    }
+    Ok(None)
+}
+
+/// Decompress a section with a zlib (GNU "ZLIB" header) or zstd frame header,
+/// sections without a known header are returned as is
+fn decompress_section(data: &[u8]) -> Result<Vec<u8>> {
+    const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+    if data.len() >= 12 && data.starts_with(b"ZLIB") {
+        let mut size = [0; 8];
+        size.copy_from_slice(&data[4..12]);
+        let size = u64::from_be_bytes(size) as usize;
+
+        let decompressed = miniz_oxide::inflate::decompress_to_vec_zlib(&data[12..])
+            .map_err(|e| anyhow!("failed to decompress zlib section: {:?}", e))?;
+        if decompressed.len() != size {
+            return Err(anyhow!(
+                "decompressed section size mismatch: expected {}, found {}",
+                size,
+                decompressed.len()
+            ));
+        }
+        Ok(decompressed)
+    } else if data.starts_with(&ZSTD_MAGIC) {
+        let mut source = data;
+        let mut decoder = ruzstd::StreamingDecoder::new(&mut source)
+            .map_err(|e| anyhow!("failed to decompress zstd section: {}", e))?;
+        let mut decompressed = Vec::new();
+        decoder.read_to_end(&mut decompressed)?;
+        Ok(decompressed)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
 /// Parsed dwarf debug data container
 pub struct DwarfDebugInfo {
     pub sourcemap: DwarfSourceMap,
     pub subroutine: DwarfSubroutineMap,
     pub global_variables: DwarfGlobalVariables,
+    /// DW_AT_producer and DWARF version of the first compilation unit
+    pub producer: Option<String>,
+    pub dwarf_version: Option<u16>,
+    pub units: Vec<UnitSummary>,
+}
+
+impl DwarfDebugInfo {
+    /// Parse the subprograms again with the split DWARF sections of dwarf_data. Line
+    /// tables stay in the skeleton units, and the settings of the maps are kept
+    pub fn load_supplementary(&mut self, dwarf_data: DwarfDebugData) -> Result<()> {
+        let debug_info = transform_dwarf_data(dwarf_data.clone())?;
+        self.subroutine.subroutines = debug_info.subroutine.subroutines;
+        self.subroutine.ranges = debug_info.subroutine.ranges;
+        self.subroutine.dwarf_data = dwarf_data.clone();
+        self.global_variables.dwarf_data = dwarf_data;
+
+        // listed groups refer to the units parsed before
+        for lazy_groups in [
+            &self.subroutine.lazy_groups,
+            &self.global_variables.lazy_groups,
+        ] {
+            lazy_groups.borrow_mut().reset(&mut Vec::new(), 0);
+        }
+        Ok(())
+    }
+}
+
+/// DW_AT_name, DW_AT_comp_dir and DW_AT_language of a compilation unit
+#[derive(Serialize)]
+pub struct UnitSummary {
+    pub name: Option<String>,
+    pub comp_dir: Option<String>,
+    /// DW_LANG_* without the prefix, e.g. "C_plus_plus_14" or "Rust"
+    pub language: Option<String>,
+    #[serde(skip)]
+    pub offset: UnitSectionOffset,
 }
 
 pub fn transform_dwarf(buffer: &[u8]) -> Result<DwarfDebugInfo> {
-    let dwarf_data = DwarfDebugData::new(buffer)?;
-    let dwarf = dwarf_data.parse_dwarf()?;
-    let mut headers = dwarf.units();
-    let mut sourcemaps = Vec::new();
-    let mut subroutines = Vec::new();
-    let mut entry_num = 0;
+    transform_dwarf_data(DwarfDebugData::new(buffer)?)
+}
 
-    while let Some(header) = headers.next()? {
-        let header_offset = header.offset();
-        let unit = dwarf.unit(header)?;
+pub fn transform_dwarf_data(dwarf_data: DwarfDebugData) -> Result<DwarfDebugInfo> {
+    let mut parser = DwarfDebugInfoParser::new(dwarf_data)?;
+    while parser.parse_next_unit()? {}
+    Ok(parser.finish())
+}
+
+/// Builds DwarfDebugInfo one unit at a time, so the host can do other work between
+/// units of a large module. The Dwarf and unit headers are those cached by DwarfDebugData
+pub struct DwarfDebugInfoParser {
+    dwarf_data: DwarfDebugData,
+    dwarf: Rc<Dwarf>,
+    dwo: Option<Rc<Dwarf>>,
+    unit_offsets: Vec<UnitSectionOffset>,
+    parsed_units: usize,
+    sourcemaps: Vec<DwarfUnitSourceMap>,
+    subroutines: Vec<Subroutine>,
+    units: Vec<UnitSummary>,
+    producer: Option<String>,
+    dwarf_version: Option<u16>,
+}
+
+impl DwarfDebugInfoParser {
+    pub fn new(dwarf_data: DwarfDebugData) -> Result<Self> {
+        // unit headers are cheap to walk, the total is known before parsing
+        let unit_offsets = dwarf_data.unit_offsets()?;
+        let dwarf = dwarf_data.cached_dwarf()?;
+        let dwo = dwarf_data.cache.borrow().dwo.clone();
+
+        Ok(Self {
+            dwarf_data,
+            dwarf,
+            dwo,
+            unit_offsets,
+            parsed_units: 0,
+            sourcemaps: Vec::new(),
+            subroutines: Vec::new(),
+            units: Vec::new(),
+            producer: None,
+            dwarf_version: None,
+        })
+    }
+
+    pub fn unit_count(&self) -> usize {
+        self.unit_offsets.len()
+    }
+
+    pub fn parsed_units(&self) -> usize {
+        self.parsed_units
+    }
+
+    /// Parse the next unit in section order, returns whether units are left
+    pub fn parse_next_unit(&mut self) -> Result<bool> {
+        let header_offset = match self.unit_offsets.get(self.parsed_units) {
+            Some(offset) => *offset,
+            None => return Ok(false),
+        };
+        self.parsed_units += 1;
+        let remaining = self.parsed_units < self.unit_offsets.len();
+
+        let header = match self.dwarf_data.cache.borrow().headers.get(&header_offset) {
+            Some(header) => header.clone(),
+            None => return Ok(remaining),
+        };
+        let dwarf = &self.dwarf;
+        let unit = parse_unit(dwarf, header)?;
         let mut entries = unit.entries();
         let root = match entries.next_dfs()? {
             Some((_, entry)) => entry,
-            None => continue,
+            None => return Ok(remaining),
         };
-        entry_num += 1;
-        sourcemaps.push(transform_debug_line(
-            &unit,
-            root,
-            &dwarf,
-            &dwarf.debug_line,
-        )?);
-        subroutines.append(&mut transform_subprogram(&dwarf, &unit, header_offset)?);
-    }
-
-    console_log!("found {} entries", entry_num);
-
-    Ok(DwarfDebugInfo {
-        sourcemap: DwarfSourceMap::new(sourcemaps, dwarf_data.clone()),
-        subroutine: DwarfSubroutineMap {
-            subroutines,
-            dwarf_data: dwarf_data.clone(),
-        },
-        global_variables: DwarfGlobalVariables { dwarf_data },
-    })
-}
+        self.units.push(UnitSummary {
+            name: match root.attr_value(gimli::DW_AT_name)? {
+                Some(attr) => Some(clone_string_attribute(dwarf, &unit, attr)?),
+                None => None,
+            },
+            comp_dir: match root.attr_value(gimli::DW_AT_comp_dir)? {
+                Some(attr) => Some(clone_string_attribute(dwarf, &unit, attr)?),
+                None => None,
+            },
+            language: match root.attr_value(gimli::DW_AT_language)? {
+                Some(AttributeValue::Language(language)) => language
+                    .static_string()
+                    .map(|name| name.trim_start_matches("DW_LANG_").to_string()),
+                _ => None,
+            },
+            offset: header_offset,
+        });
+        if self.dwarf_version.is_none() {
+            self.dwarf_version = Some(unit.header.version());
+            self.producer = match root.attr_value(gimli::DW_AT_producer)? {
+                Some(attr) => Some(clone_string_attribute(dwarf, &unit, attr)?),
+                None => None,
+            };
+        }
+        self.sourcemaps
+            .push(transform_debug_line(&unit, root, dwarf, &dwarf.debug_line)?);
 
-fn header_from_offset<R: gimli::Reader>(
-    dwarf: &gimli::Dwarf<R>,
-    offset: UnitSectionOffset<R::Offset>,
-) -> Result<Option<UnitHeader<R>>> {
-    let mut headers = dwarf.units();
-    while let Some(header) = headers.next()? {
-        if header.offset() == offset {
-            return Ok(Some(header));
-        } else {
-            continue;
+        // skeleton units keep the line table, but subprograms live in the split unit
+        let split = match self.dwo {
+            Some(ref dwo) => split_unit(dwo, &unit)?.map(|split| (dwo, split)),
+            None => None,
+        };
+        let mut subroutines = match split {
+            Some((dwo, split)) => transform_subprogram(dwo, &split, header_offset)?,
+            None => transform_subprogram(dwarf, &unit, header_offset)?,
+        };
+        self.subroutines.append(&mut subroutines);
+        Ok(remaining)
+    }
+
+    /// Debug info of the units parsed so far
+    pub fn finish(self) -> DwarfDebugInfo {
+        console_log!("found {} entries", self.units.len());
+
+        DwarfDebugInfo {
+            sourcemap: DwarfSourceMap::new(self.sourcemaps),
+            subroutine: DwarfSubroutineMap {
+                ranges: index_subroutine_ranges(&self.subroutines),
+                subroutines: self.subroutines,
+                dwarf_data: self.dwarf_data.clone(),
+                lazy_groups: RefCell::new(LazyGroups::default()),
+                rbp_local_offset: DEFAULT_RBP_LOCAL_OFFSET,
+                data_base: 0,
+            },
+            global_variables: DwarfGlobalVariables {
+                dwarf_data: self.dwarf_data,
+                lazy_groups: RefCell::new(LazyGroups::default()),
+            },
+            producer: self.producer,
+            dwarf_version: self.dwarf_version,
+            units: self.units,
         }
     }
-    Ok(None)
 }
 
 fn unit_type_name<R: gimli::Reader>(
@@ -158,21 +539,146 @@ fn unit_type_name<R: gimli::Reader>(
     let root = tree.root()?;
 
     match root.entry().tag() {
-        gimli::DW_TAG_base_type
-        | gimli::DW_TAG_class_type
-        | gimli::DW_TAG_structure_type
-        | gimli::DW_TAG_union_type => {
+        gimli::DW_TAG_base_type | gimli::DW_TAG_enumeration_type => {
             if let Some(attr) = root.entry().attr_value(gimli::DW_AT_name)? {
                 clone_string_attribute(dwarf, unit, attr)
             } else {
                 Ok(String::from("<no-type-name>"))
             }
         }
+        gimli::DW_TAG_class_type | gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type => {
+            let name = match root.entry().attr_value(gimli::DW_AT_name)? {
+                Some(attr) => clone_string_attribute(dwarf, unit, attr)?,
+                None => return Ok(String::from("<no-type-name>")),
+            };
+            if name.contains('<') {
+                return Ok(name);
+            }
+
+            // some producers leave the template arguments out of the name
+            let mut arguments = Vec::new();
+            let mut children = root.children();
+            while let Some(child) = children.next()? {
+                let entry = child.entry();
+                match entry.tag() {
+                    gimli::DW_TAG_template_type_parameter => {
+                        let ty = match entry.attr_value(gimli::DW_AT_type)? {
+                            Some(AttributeValue::UnitRef(ref offset)) => Some(offset.0),
+                            _ => None,
+                        };
+                        arguments.push(unit_type_name(dwarf, unit, ty)?);
+                    }
+                    gimli::DW_TAG_template_value_parameter => {
+                        let value = match entry.attr_value(gimli::DW_AT_const_value)? {
+                            Some(AttributeValue::Sdata(v)) => Some(v.to_string()),
+                            Some(attr) => attr.udata_value().map(|v| v.to_string()),
+                            None => None,
+                        };
+                        arguments.push(value.unwrap_or_else(|| String::from("?")));
+                    }
+                    _ => {}
+                }
+            }
+
+            if arguments.is_empty() {
+                Ok(name)
+            } else {
+                Ok(format!("{}<{}>", name, arguments.join(", ")))
+            }
+        }
+        gimli::DW_TAG_typedef => {
+            if let Some(attr) = root.entry().attr_value(gimli::DW_AT_name)? {
+                clone_string_attribute(dwarf, unit, attr)
+            } else {
+                let inner = match root.entry().attr_value(gimli::DW_AT_type)? {
+                    Some(AttributeValue::UnitRef(ref offset)) => Some(offset.0),
+                    _ => None,
+                };
+                unit_type_name(dwarf, unit, inner)
+            }
+        }
+        gimli::DW_TAG_const_type
+        | gimli::DW_TAG_volatile_type
+        | gimli::DW_TAG_restrict_type
+        | gimli::DW_TAG_atomic_type => {
+            let qualifier = match root.entry().tag() {
+                gimli::DW_TAG_const_type => "const",
+                gimli::DW_TAG_volatile_type => "volatile",
+                gimli::DW_TAG_atomic_type => "_Atomic",
+                _ => "restrict",
+            };
+            let inner = match root.entry().attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(ref offset)) => Some(offset.0),
+                _ => None,
+            };
+            Ok(format!(
+                "{} {}",
+                qualifier,
+                unit_type_name(dwarf, unit, inner)?
+            ))
+        }
+        gimli::DW_TAG_ptr_to_member_type => {
+            let class_name = match root.entry().attr_value(gimli::DW_AT_containing_type)? {
+                Some(AttributeValue::UnitRef(ref offset)) => {
+                    unit_type_name(dwarf, unit, Some(offset.0))?
+                }
+                _ => String::from("<no-type-name>"),
+            };
+            let member_offset = match root.entry().attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(ref offset)) => Some(offset.0),
+                _ => None,
+            };
+            let member_name = unit_type_name(dwarf, unit, member_offset)?;
+
+            // member functions read "int (Foo::*)(char)", data members "int Foo::*"
+            let is_function = match member_offset {
+                Some(offset) => {
+                    unit.entry(UnitOffset(offset))?.tag() == gimli::DW_TAG_subroutine_type
+                }
+                None => false,
+            };
+            if is_function {
+                Ok(member_name.replacen("(*)", &format!("({}::*)", class_name), 1))
+            } else {
+                Ok(format!("{} {}::*", member_name, class_name))
+            }
+        }
+        // function types are named as pointers, they are only reachable through one
+        gimli::DW_TAG_subroutine_type => {
+            let return_type = match root.entry().attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(ref offset)) => Some(offset.0),
+                _ => None,
+            };
+
+            let mut parameters = Vec::new();
+            let mut children = root.children();
+            while let Some(child) = children.next()? {
+                match child.entry().tag() {
+                    gimli::DW_TAG_formal_parameter => {
+                        let ty = match child.entry().attr_value(gimli::DW_AT_type)? {
+                            Some(AttributeValue::UnitRef(ref offset)) => Some(offset.0),
+                            _ => None,
+                        };
+                        parameters.push(unit_type_name(dwarf, unit, ty)?);
+                    }
+                    gimli::DW_TAG_unspecified_parameters => parameters.push("...".to_string()),
+                    _ => {}
+                }
+            }
+
+            Ok(format!(
+                "{} (*)({})",
+                unit_type_name(dwarf, unit, return_type)?,
+                parameters.join(", ")
+            ))
+        }
         _ => {
             if let Some(AttributeValue::UnitRef(ref offset)) =
                 root.entry().attr_value(gimli::DW_AT_type)?
             {
                 unit_type_name(dwarf, unit, Some(offset.0))
+            } else if root.entry().tag() == gimli::DW_TAG_pointer_type {
+                Ok(String::from("void *"))
             } else {
                 Err(anyhow!(format!("failed to seek at {:?}", type_offset)))
             }
@@ -183,7 +689,8 @@ fn unit_type_name<R: gimli::Reader>(
 #[wasm_bindgen]
 #[derive(Clone)]
 pub struct MemorySlice {
-    pub address: usize,
+    /// u64 to hold memory64 addresses
+    pub address: u64,
     pub byte_size: usize,
 
     memory_slice: Vec<u8>,
@@ -215,7 +722,7 @@ impl MemorySlice {
 enum VariableEvaluationResult {
     Ready,
     Complete,
-    RequireMemorySlice(MemorySlice),
+    RequireMemorySlice(#[allow(dead_code)] MemorySlice),
 }
 
 #[wasm_bindgen]
@@ -225,15 +732,104 @@ pub struct VariableInfo {
     pub(crate) address_expr: Vec<VariableLocation>,
     pub(crate) byte_size: usize,
     pub(crate) memory_slice: MemorySlice,
+    /// Size of the pointers dereferenced by VariableLocation::Pointer, 8 on memory64
+    pub(crate) pointer_size: usize,
 
     state: VariableEvaluationResult,
 
     tag: gimli::DwTag,
     encoding: gimli::DwAte,
+    byte_order: gimli::RunTimeEndian,
+    enumerators: Vec<(String, BigInt)>,
+    /// Bitfield position in bits from the least significant bit of the loaded bytes
+    bit_offset: Option<u64>,
+    bit_size: Option<u64>,
+    /// Layout of a variable split by DW_OP_piece, empty for single location variables
+    pieces: Vec<VariablePiece>,
+    radix: DisplayRadix,
+    pretty: Option<PrettyLayout>,
+    /// Expression the variable was looked up with
+    variable_name: String,
+    /// Scale of fixed-point base types
+    fixed_scale: Option<FixedScale>,
+    /// Number of members of aggregates
+    children: Option<usize>,
+}
+
+/// Upper limit of the text read for a pretty printed string
+const MAX_PRETTY_STRING_LENGTH: usize = 4096;
+
+fn member_value(
+    bytes: &[u8],
+    layout: MemberLayout,
+    byte_order: gimli::RunTimeEndian,
+) -> Option<u64> {
+    bytes
+        .get(layout.offset..layout.offset + layout.byte_size)
+        .map(|bytes| {
+            unsigned_from_bytes(bytes, byte_order)
+                .to_u64_digits()
+                .first()
+                .copied()
+                .unwrap_or(0)
+        })
+}
+
+/// Bytes of a piece from its read, which has to cover the whole piece
+fn piece_bytes(address: u64, byte_size: usize, memory: Option<MemorySlice>) -> Result<Vec<u8>> {
+    let mut bytes = match memory {
+        Some(memory) => memory.memory_slice,
+        None => return Err(anyhow!("the piece at 0x{:x} was not read", address)),
+    };
+    if bytes.len() < byte_size {
+        return Err(anyhow!(
+            "read {} of the {} bytes of the piece at 0x{:x}",
+            bytes.len(),
+            byte_size,
+            address
+        ));
+    }
+    bytes.truncate(byte_size);
+    Ok(bytes)
+}
+
+impl VariableInfo {
+    /// Value of the named type, the layout and display fields start out empty
+    pub(crate) fn new(
+        name: String,
+        tag: gimli::DwTag,
+        encoding: gimli::DwAte,
+        byte_order: gimli::RunTimeEndian,
+    ) -> Self {
+        Self {
+            name,
+            address_expr: Vec::new(),
+            byte_size: 0,
+            memory_slice: MemorySlice::new(),
+            pointer_size: 4,
+            state: VariableEvaluationResult::Ready,
+            tag,
+            encoding,
+            byte_order,
+            enumerators: Vec::new(),
+            bit_offset: None,
+            bit_size: None,
+            pieces: Vec::new(),
+            radix: DisplayRadix::Decimal,
+            pretty: None,
+            variable_name: String::new(),
+            fixed_scale: None,
+            children: None,
+        }
+    }
 }
 
 #[wasm_bindgen]
 impl VariableInfo {
+    pub fn set_radix(&mut self, radix: DisplayRadix) {
+        self.radix = radix;
+    }
+
     pub fn evaluate(&mut self) -> Option<String> {
         match self.state {
             VariableEvaluationResult::Ready => {}
@@ -242,19 +838,30 @@ impl VariableInfo {
             }
         }
 
-        if self.address_expr.is_empty() {
-            self.state = VariableEvaluationResult::Complete;
+        if !self.pieces.is_empty() {
+            return self.evaluate_pieces();
+        }
 
-            match format_object(self) {
-                Ok(x) => Some(x),
-                Err(_) => None,
-            }
+        if self.address_expr.is_empty() {
+            self.complete()
         } else {
             self.evaluate_internal();
             None
         }
     }
 
+    /// Same as evaluate, but the result is a JSON object with name, type, value,
+    /// encoding and children
+    pub fn evaluate_json(&mut self) -> Option<String> {
+        self.evaluate().map(|display| format_json(self, &display))
+    }
+
+    /// Same as resume_with_memory_slice, with the result of evaluate_json
+    pub fn resume_with_memory_slice_json(&mut self, memory: MemorySlice) -> Option<String> {
+        self.resume_with_memory_slice(memory)
+            .map(|display| format_json(self, &display))
+    }
+
     pub fn resume_with_memory_slice(&mut self, memory: MemorySlice) -> Option<String> {
         match self.state {
             VariableEvaluationResult::RequireMemorySlice(_) => {}
@@ -263,31 +870,179 @@ impl VariableInfo {
             }
         }
 
+        if !self.pieces.is_empty() {
+            let piece = self
+                .pieces
+                .iter_mut()
+                .find(|piece| matches!(piece, VariablePiece::Memory { .. }));
+            if let Some(piece) = piece {
+                if let VariablePiece::Memory { address, byte_size } = *piece {
+                    match piece_bytes(address, byte_size, Some(memory)) {
+                        Ok(bytes) => *piece = VariablePiece::Value(bytes),
+                        Err(e) => return self.fail(e),
+                    }
+                }
+            }
+            return self.evaluate_pieces();
+        }
+
         if let Some(VariableLocation::Pointer) = self.address_expr.first() {
             self.address_expr.remove(0);
             self.address_expr.insert(
                 0,
                 VariableLocation::Address(
-                    BigUint::from_bytes_le(&memory.memory_slice).to_u64_digits()[0],
+                    unsigned_from_bytes(&memory.memory_slice, self.byte_order)
+                        .to_u64_digits()
+                        .first()
+                        .copied()
+                        .unwrap_or(0),
                 ),
             );
         }
 
+        if let Some(&VariableLocation::Scaled { bias, scale, .. }) = self.address_expr.first() {
+            self.address_expr.remove(0);
+            let bound = unsigned_from_bytes(&memory.memory_slice, self.byte_order)
+                .to_u64_digits()
+                .first()
+                .copied()
+                .unwrap_or(0);
+            // the address saved by evaluate_internal is next
+            self.address_expr
+                .insert(1, VariableLocation::Offset((bound as i64 + bias) * scale));
+        }
+
+        if let Some(VariableLocation::Member { .. }) = self.address_expr.first() {
+            let mut member = self.address_expr.remove(0);
+            if let VariableLocation::Member { reads, .. } = &mut member {
+                reads.push(memory.memory_slice.clone());
+            }
+            // the address saved by evaluate_internal is next
+            self.address_expr.insert(1, member);
+        }
+
         self.memory_slice = memory;
 
         if self.address_expr.is_empty() {
-            self.state = VariableEvaluationResult::Complete;
-
-            match format_object(self) {
-                Ok(x) => Some(x),
-                Err(_) => None,
-            }
+            self.complete()
         } else {
             self.evaluate_internal();
             None
         }
     }
 
+    /// Format the loaded value, or request the text of a pretty printed string
+    fn complete(&mut self) -> Option<String> {
+        match self.pretty.take() {
+            Some(PrettyLayout::String { data, length }) => {
+                let bytes = &self.memory_slice.memory_slice;
+                let address = member_value(bytes, data, self.byte_order);
+                let text_length = member_value(bytes, length, self.byte_order);
+
+                let (address, text_length) = match (address, text_length) {
+                    (Some(address), Some(text_length)) => (address, text_length as usize),
+                    _ => {
+                        self.state = VariableEvaluationResult::Complete;
+                        return format_object(self).ok();
+                    }
+                };
+
+                if text_length == 0 {
+                    self.memory_slice = MemorySlice::new();
+                    self.state = VariableEvaluationResult::Complete;
+                    return Some(format_text(self, 0));
+                }
+
+                let slice = MemorySlice {
+                    address,
+                    byte_size: std::cmp::min(text_length, MAX_PRETTY_STRING_LENGTH),
+                    memory_slice: Vec::new(),
+                };
+
+                self.pretty = Some(PrettyLayout::Text {
+                    length: text_length,
+                });
+                self.memory_slice = slice.clone();
+                self.state = VariableEvaluationResult::RequireMemorySlice(slice);
+                None
+            }
+            Some(PrettyLayout::Text { length }) => {
+                self.state = VariableEvaluationResult::Complete;
+                Some(format_text(self, length))
+            }
+            Some(PrettyLayout::Variant {
+                discriminant,
+                variants,
+            }) => {
+                self.state = VariableEvaluationResult::Complete;
+                let value = member_value(
+                    &self.memory_slice.memory_slice,
+                    discriminant,
+                    self.byte_order,
+                );
+                let variant = variants
+                    .iter()
+                    .find(|variant| value.is_some() && variant.discr_value == value)
+                    .or_else(|| {
+                        variants
+                            .iter()
+                            .find(|variant| variant.discr_value.is_none())
+                    });
+
+                match variant {
+                    Some(variant) => Some(format_variant(self, variant)),
+                    None => format_object(self).ok(),
+                }
+            }
+            None => {
+                self.state = VariableEvaluationResult::Complete;
+                format_object(self).ok()
+            }
+        }
+    }
+
+    /// End the evaluation without a value, as reads which can't be used would only be
+    /// requested again
+    fn fail(&mut self, e: anyhow::Error) -> Option<String> {
+        console_log!("{}", e);
+        self.state = VariableEvaluationResult::Complete;
+        None
+    }
+
+    /// Request the memory of the next piece, or assemble the value once every piece is loaded
+    fn evaluate_pieces(&mut self) -> Option<String> {
+        let next = self.pieces.iter().find_map(|piece| match piece {
+            VariablePiece::Memory { address, byte_size } => Some((*address, *byte_size)),
+            VariablePiece::Value(_) => None,
+        });
+
+        match next {
+            Some((address, byte_size)) => {
+                let slice = MemorySlice {
+                    address,
+                    byte_size,
+                    memory_slice: Vec::new(),
+                };
+
+                self.memory_slice = slice.clone();
+                self.state = VariableEvaluationResult::RequireMemorySlice(slice);
+                None
+            }
+            None => {
+                let mut data = Vec::new();
+                for piece in &self.pieces {
+                    if let VariablePiece::Value(bytes) = piece {
+                        data.extend_from_slice(bytes);
+                    }
+                }
+
+                self.memory_slice = MemorySlice::from_u8_vec(data);
+                self.state = VariableEvaluationResult::Complete;
+                format_object(self).ok()
+            }
+        }
+    }
+
     fn evaluate_internal(&mut self) {
         let mut address = 0;
         let mut byte_size = self.byte_size;
@@ -299,15 +1054,76 @@ impl VariableInfo {
                 }
                 VariableLocation::Offset(off) => address = (address as i64 + off) as u64,
                 VariableLocation::Pointer => {
-                    byte_size = 4;
+                    byte_size = self.pointer_size;
                     self.address_expr.insert(0, VariableLocation::Pointer);
                     break;
                 }
+                // read the bound, then continue from the current address
+                VariableLocation::Scaled {
+                    address: bound_address,
+                    byte_size: bound_size,
+                    bias,
+                    scale,
+                } => {
+                    self.address_expr
+                        .insert(0, VariableLocation::Address(address));
+                    self.address_expr.insert(
+                        0,
+                        VariableLocation::Scaled {
+                            address: bound_address,
+                            byte_size: bound_size,
+                            bias,
+                            scale,
+                        },
+                    );
+                    address = bound_address;
+                    byte_size = bound_size;
+                    break;
+                }
+                // load what the expression reads, then evaluate it again with the result
+                VariableLocation::Member {
+                    expression,
+                    encoding,
+                    reads,
+                } => {
+                    match evaluate_member_location(
+                        encoding,
+                        expression.clone(),
+                        address,
+                        &reads,
+                        self.byte_order,
+                    ) {
+                        Ok(MemberAddress::Resolved(member)) => address = member,
+                        Ok(MemberAddress::RequiresMemory {
+                            address: read_address,
+                            byte_size: read_size,
+                        }) => {
+                            self.address_expr
+                                .insert(0, VariableLocation::Address(address));
+                            self.address_expr.insert(
+                                0,
+                                VariableLocation::Member {
+                                    expression,
+                                    encoding,
+                                    reads,
+                                },
+                            );
+                            address = read_address;
+                            byte_size = read_size;
+                            break;
+                        }
+                        Err(e) => {
+                            console_log!("{}", e);
+                            self.state = VariableEvaluationResult::Complete;
+                            return;
+                        }
+                    }
+                }
             }
         }
 
         let slice = MemorySlice {
-            address: address as usize,
+            address,
             byte_size,
             memory_slice: Vec::new(),
         };
@@ -317,20 +1133,281 @@ impl VariableInfo {
     }
 
     pub fn is_required_memory_slice(&self) -> bool {
+        matches!(self.state, VariableEvaluationResult::RequireMemorySlice(_))
+    }
+
+    pub fn is_completed(&self) -> bool {
+        matches!(self.state, VariableEvaluationResult::Complete)
+    }
+
+    pub fn required_memory_slice(&self) -> MemorySlice {
+        self.memory_slice.clone()
+    }
+
+    /// Every read which can be made now, the pieces of a split variable are requested at once
+    pub fn required_memory_slices(&self) -> MemorySliceVector {
+        let mut slices = MemorySliceVector::new();
+        if !self.is_required_memory_slice() {
+            return slices;
+        }
+
+        if self.pieces.is_empty() {
+            slices.push(self.memory_slice.clone());
+            return slices;
+        }
+
+        for piece in &self.pieces {
+            if let VariablePiece::Memory { address, byte_size } = *piece {
+                slices.push(MemorySlice {
+                    address,
+                    byte_size,
+                    memory_slice: Vec::new(),
+                });
+            }
+        }
+        slices
+    }
+
+    /// Resume with the reads of required_memory_slices, and return the value once complete
+    pub fn resume_with_memory_slices(&mut self, memories: MemorySliceVector) -> Option<String> {
+        if !self.is_required_memory_slice() {
+            return None;
+        }
+
+        let mut memories = memories.data;
+        if self.pieces.is_empty() {
+            if memories.is_empty() {
+                return None;
+            }
+            return self.resume_with_memory_slice(memories.remove(0));
+        }
+
+        for index in 0..self.pieces.len() {
+            if let VariablePiece::Memory { address, byte_size } = self.pieces[index] {
+                let memory = memories
+                    .iter()
+                    .position(|m| m.address == address)
+                    .map(|i| memories.remove(i));
+                match piece_bytes(address, byte_size, memory) {
+                    Ok(bytes) => self.pieces[index] = VariablePiece::Value(bytes),
+                    Err(e) => return self.fail(e),
+                }
+            }
+        }
+        self.evaluate_pieces()
+    }
+
+    /// Integer value of a scalar variable for breakpoint conditions. Values held in wasm
+    /// locals or constants are returned at once; for values in linear memory this starts
+    /// the evaluation, and is_required_memory_slice tells the read to make before retrying
+    pub fn integer_value(&mut self) -> Option<i64> {
+        if let VariableEvaluationResult::Ready = self.state {
+            self.evaluate();
+        }
+
         match self.state {
-            VariableEvaluationResult::RequireMemorySlice(_) => true,
-            _ => false,
+            VariableEvaluationResult::Complete => integer_value(self),
+            _ => None,
         }
     }
 
-    pub fn is_completed(&self) -> bool {
+    /// Bytes of the value loaded by the evaluation, empty until it has completed
+    pub fn raw_bytes(&self) -> Vec<u8> {
         match self.state {
-            VariableEvaluationResult::Complete => true,
-            _ => false,
+            VariableEvaluationResult::Complete => self.memory_slice.memory_slice.clone(),
+            _ => Vec::new(),
         }
     }
 
-    pub fn required_memory_slice(&self) -> MemorySlice {
-        self.memory_slice.clone()
+    /// Linear memory address the value was loaded from, 0 for values held in wasm locals
+    pub fn address(&self) -> u64 {
+        match self.state {
+            VariableEvaluationResult::Complete => self.memory_slice.address,
+            _ => 0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dwarf::test_utils::{debug_data, wasm_module};
+
+    /// Sections of a DWARF 5 compile unit whose name and low_pc are DW_FORM_strx1 /
+    /// DW_FORM_addrx1 with index 1, optionally with explicit bases
+    fn indexed_unit(with_bases: bool) -> HashMap<String, Vec<u8>> {
+        let mut abbrev = vec![1, 0x11, 0, 0x03, 0x25, 0x11, 0x29];
+        let mut die = vec![1, 1, 1];
+        if with_bases {
+            abbrev.extend_from_slice(&[0x72, 0x17, 0x73, 0x17]);
+            die.extend_from_slice(&8u32.to_le_bytes());
+            die.extend_from_slice(&8u32.to_le_bytes());
+        }
+        abbrev.extend_from_slice(&[0, 0, 0]);
+
+        // version 5, DW_UT_compile, 4 byte addresses, abbreviations at 0
+        let mut info_body = vec![5, 0, 1, 4, 0, 0, 0, 0];
+        info_body.append(&mut die);
+        let mut info = (info_body.len() as u32).to_le_bytes().to_vec();
+        info.append(&mut info_body);
+
+        let mut str_offsets = vec![12, 0, 0, 0, 5, 0, 0, 0];
+        for offset in [7u32, 0] {
+            str_offsets.extend_from_slice(&offset.to_le_bytes());
+        }
+        let mut addr = vec![12, 0, 0, 0, 5, 0, 4, 0];
+        for address in [0x1000u32, 0x2000] {
+            addr.extend_from_slice(&address.to_le_bytes());
+        }
+
+        let mut sections = HashMap::new();
+        sections.insert(String::from(".debug_abbrev"), abbrev);
+        sections.insert(String::from(".debug_info"), info);
+        sections.insert(String::from(".debug_str"), b"main.c\0lib.c\0".to_vec());
+        sections.insert(String::from(".debug_str_offsets"), str_offsets);
+        sections.insert(String::from(".debug_addr"), addr);
+        sections
+    }
+
+    fn name_and_low_pc(sections: &HashMap<String, Vec<u8>>) -> (String, u64) {
+        let data = debug_data(sections);
+        let offset = data.unit_offsets().unwrap()[0];
+        let (dwarf, unit) = data.unit_offset(offset).unwrap().unwrap();
+        let mut entries = unit.entries();
+        let (_, root) = entries.next_dfs().unwrap().unwrap();
+
+        let name = root.attr_value(gimli::DW_AT_name).unwrap().unwrap();
+        let low_pc = root.attr_value(gimli::DW_AT_low_pc).unwrap().unwrap();
+        (
+            clone_string_attribute(&dwarf, &unit, name).unwrap(),
+            dwarf.attr_address(&unit, low_pc).unwrap().unwrap(),
+        )
+    }
+
+    #[test]
+    fn indexed_strings_and_addresses_are_read_through_their_bases() {
+        let expected = (String::from("main.c"), 0x2000);
+        assert_eq!(name_and_low_pc(&indexed_unit(true)), expected);
+        // without DW_AT_str_offsets_base / DW_AT_addr_base the first contribution is used
+        assert_eq!(name_and_low_pc(&indexed_unit(false)), expected);
+    }
+
+    /// GNU zlib section: "ZLIB", the big endian size, then the zlib stream
+    fn zlib_section(data: &[u8], size: u64) -> Vec<u8> {
+        let mut section = b"ZLIB".to_vec();
+        section.extend_from_slice(&size.to_be_bytes());
+        section.append(&mut miniz_oxide::deflate::compress_to_vec_zlib(data, 6));
+        section
+    }
+
+    #[test]
+    fn zlib_sections_are_decompressed() {
+        let data = b"int main(void) { return 0; }".repeat(8);
+        let section = zlib_section(&data, data.len() as u64);
+        assert!(section.len() < data.len());
+        assert_eq!(decompress_section(&section).unwrap(), data);
+    }
+
+    #[test]
+    fn zlib_sections_of_another_size_are_rejected() {
+        let data = b"main.c".to_vec();
+        let error = decompress_section(&zlib_section(&data, 7)).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "decompressed section size mismatch: expected 7, found 6"
+        );
+    }
+
+    #[test]
+    fn zstd_sections_are_decompressed() {
+        let data = b"main.c".to_vec();
+        // single segment frame with a 1 byte content size, and a last raw block
+        let mut frame = vec![0x28, 0xb5, 0x2f, 0xfd, 0x20, data.len() as u8];
+        let block_header = 1 | (data.len() as u32) << 3;
+        frame.extend_from_slice(&block_header.to_le_bytes()[..3]);
+        frame.extend_from_slice(&data);
+        assert_eq!(decompress_section(&frame).unwrap(), data);
+    }
+
+    #[test]
+    fn zdebug_sections_are_renamed_to_debug() {
+        let data = b"main.c\0".to_vec();
+        let mut sections = HashMap::new();
+        sections.insert(
+            ".zdebug_str".to_string(),
+            zlib_section(&data, data.len() as u64),
+        );
+        sections.insert(".debug_line".to_string(), vec![1, 2, 3]);
+
+        let dwarf_data = DwarfDebugData::new(&wasm_module(&sections)).unwrap();
+        let mut names: Vec<&str> = dwarf_data
+            .program_raw_data
+            .keys()
+            .map(|name| name.as_str())
+            .collect();
+        names.sort_unstable();
+        assert_eq!(names, [".debug_line", ".debug_str"]);
+        assert_eq!(&*dwarf_data.program_raw_data[".debug_str"], &data[..]);
+        // uncompressed sections are kept as they are
+        assert_eq!(&*dwarf_data.program_raw_data[".debug_line"], &[1, 2, 3]);
+    }
+
+    #[test]
+    fn units_are_parsed_from_the_cached_dwarf() {
+        let dwarf_data = debug_data(&indexed_unit(true));
+        let parser = DwarfDebugInfoParser::new(dwarf_data.clone()).unwrap();
+        let dwarf = dwarf_data.cached_dwarf().unwrap();
+        assert!(Rc::ptr_eq(&parser.dwarf, &dwarf));
+    }
+
+    /// An int split into two 2 byte pieces of linear memory, evaluated with the reads
+    /// of the given (address, bytes)
+    fn evaluate_split_int(reads: &[(u64, &[u8])]) -> (Option<String>, bool) {
+        let mut info = VariableInfo {
+            byte_size: 4,
+            pieces: vec![
+                VariablePiece::Memory {
+                    address: 0x100,
+                    byte_size: 2,
+                },
+                VariablePiece::Memory {
+                    address: 0x200,
+                    byte_size: 2,
+                },
+            ],
+            ..VariableInfo::new(
+                String::from("int"),
+                gimli::DW_TAG_base_type,
+                gimli::DW_ATE_signed,
+                gimli::RunTimeEndian::Little,
+            )
+        };
+        assert_eq!(info.evaluate(), None);
+        assert_eq!(info.required_memory_slices().size(), 2);
+
+        let mut memories = MemorySliceVector::new();
+        for (address, bytes) in reads {
+            memories.push(MemorySlice {
+                address: *address,
+                byte_size: bytes.len(),
+                memory_slice: bytes.to_vec(),
+            });
+        }
+        let result = info.resume_with_memory_slices(memories);
+        (result, info.is_required_memory_slice())
+    }
+
+    #[test]
+    fn pieces_fail_on_short_or_missing_reads() {
+        assert_eq!(
+            evaluate_split_int(&[(0x200, &[2, 0]), (0x100, &[1, 0])]),
+            (Some(String::from("(int)131073")), false)
+        );
+        // neither is requested again
+        assert_eq!(
+            evaluate_split_int(&[(0x100, &[1, 0]), (0x200, &[2])]),
+            (None, false)
+        );
+        assert_eq!(evaluate_split_int(&[(0x100, &[1, 0])]), (None, false));
     }
 }
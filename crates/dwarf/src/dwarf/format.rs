@@ -1,50 +1,402 @@
 use anyhow::{anyhow, Result};
+use gimli::{Endianity, RunTimeEndian};
 use num_bigint::{BigInt, BigUint};
+use serde::Serialize;
+use std::convert::TryFrom;
 
-use super::VariableInfo;
+use super::pretty_printer::{VariantField, VariantName};
+use super::{MemorySlice, VariableInfo};
+use wasm_bindgen::prelude::*;
+
+/// Radix of integer values, floats and structs are not affected
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DisplayRadix {
+    Decimal,
+    Hex,
+    Binary,
+}
+
+/// Scale of a fixed-point base type, its value is the stored integer times the scale
+#[derive(Copy, Clone, Debug)]
+pub enum FixedScale {
+    /// DW_AT_binary_scale: times 2^n
+    Binary(i64),
+    /// DW_AT_decimal_scale: times 10^n
+    Decimal(i64),
+}
+
+fn format_integer<T: std::fmt::Display + std::fmt::LowerHex + std::fmt::Binary>(
+    value: T,
+    radix: DisplayRadix,
+) -> String {
+    match radix {
+        DisplayRadix::Decimal => format!("{}", value),
+        DisplayRadix::Hex => format!("{:#x}", value),
+        DisplayRadix::Binary => format!("{:#b}", value),
+    }
+}
+
+pub(crate) fn unsigned_from_bytes(bytes: &[u8], byte_order: RunTimeEndian) -> BigUint {
+    if byte_order.is_big_endian() {
+        BigUint::from_bytes_be(bytes)
+    } else {
+        BigUint::from_bytes_le(bytes)
+    }
+}
+
+pub(crate) fn signed_from_bytes(bytes: &[u8], byte_order: RunTimeEndian) -> BigInt {
+    if byte_order.is_big_endian() {
+        BigInt::from_signed_bytes_be(bytes)
+    } else {
+        BigInt::from_signed_bytes_le(bytes)
+    }
+}
+
+/// Mask and shift a bitfield out of the loaded storage bytes
+fn bitfield_value(varinfo: &VariableInfo, bit_size: u64) -> BigInt {
+    let bit_offset = varinfo.bit_offset.unwrap_or(0) as usize;
+    let bit_size = bit_size as usize;
+    let raw = unsigned_from_bytes(&varinfo.memory_slice.memory_slice, varinfo.byte_order);
+    let one = BigUint::from(1u32);
+    let mask = (&one << bit_size) - &one;
+    let bits = (raw >> bit_offset) & mask;
+
+    let signed = matches!(
+        varinfo.encoding,
+        gimli::DW_ATE_signed | gimli::DW_ATE_signed_char
+    );
+    if signed && bit_size > 0 && (&bits >> (bit_size - 1)) == one {
+        BigInt::from(bits) - (BigInt::from(1) << bit_size)
+    } else {
+        BigInt::from(bits)
+    }
+}
+
+fn format_bitfield(varinfo: &VariableInfo, bit_size: u64) -> Result<String> {
+    let name = &varinfo.name;
+    let value = bitfield_value(varinfo, bit_size);
+
+    match varinfo.tag {
+        gimli::DW_TAG_enumeration_type => {
+            match varinfo.enumerators.iter().find(|(_, v)| *v == value) {
+                Some((enumerator, _)) => Ok(format!("({}){}", name, enumerator)),
+                None => Ok(format!(
+                    "({}){}",
+                    name,
+                    format_integer(value, varinfo.radix)
+                )),
+            }
+        }
+        gimli::DW_TAG_base_type => match varinfo.encoding {
+            gimli::DW_ATE_boolean => Ok(format!("({}){}", name, value != BigInt::from(0))),
+            gimli::DW_ATE_signed
+            | gimli::DW_ATE_signed_char
+            | gimli::DW_ATE_unsigned
+            | gimli::DW_ATE_unsigned_char => Ok(format!(
+                "({}){}",
+                name,
+                format_integer(value, varinfo.radix)
+            )),
+            other => Err(anyhow!(format!("unsupported bitfield type: {}", other))),
+        },
+        _ => Err(anyhow!("unsupported bitfield DIE type")),
+    }
+}
+
+/// Render the text read for a pretty printed string of length bytes
+pub fn format_text(varinfo: &VariableInfo, length: usize) -> String {
+    let bytes = &varinfo.memory_slice.memory_slice;
+    let text = String::from_utf8_lossy(&bytes[..std::cmp::min(bytes.len(), length)]);
+
+    if length > bytes.len() {
+        format!("({}){:?}... (length {})", varinfo.name, text, length)
+    } else {
+        format!("({}){:?} (length {})", varinfo.name, text, length)
+    }
+}
+
+/// The first byte_size bytes of the loaded memory, an error for a short read
+fn loaded_bytes(varinfo: &VariableInfo, byte_size: usize) -> Result<&[u8]> {
+    let memory = &varinfo.memory_slice.memory_slice;
+    memory
+        .get(0..byte_size)
+        .ok_or_else(|| anyhow!("expected {} bytes, got {}", byte_size, memory.len()))
+}
+
+/// Integer value of a loaded integer, boolean, enum or pointer, None for other types
+pub fn integer_value(varinfo: &VariableInfo) -> Option<i64> {
+    match varinfo.tag {
+        gimli::DW_TAG_base_type => match varinfo.encoding {
+            gimli::DW_ATE_boolean
+            | gimli::DW_ATE_signed
+            | gimli::DW_ATE_signed_char
+            | gimli::DW_ATE_unsigned
+            | gimli::DW_ATE_unsigned_char => {}
+            _ => return None,
+        },
+        gimli::DW_TAG_enumeration_type
+        | gimli::DW_TAG_pointer_type
+        | gimli::DW_TAG_reference_type => {}
+        _ => return None,
+    }
+
+    let value = match varinfo.bit_size {
+        Some(bit_size) => bitfield_value(varinfo, bit_size),
+        None => {
+            let bytes = loaded_bytes(varinfo, varinfo.byte_size).ok()?;
+            match varinfo.encoding {
+                gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => {
+                    signed_from_bytes(bytes, varinfo.byte_order)
+                }
+                _ => BigInt::from(unsigned_from_bytes(bytes, varinfo.byte_order)),
+            }
+        }
+    };
+    i64::try_from(&value).ok()
+}
+
+/// Render a Rust enum by the name of its active variant followed by its fields
+pub fn format_variant(varinfo: &VariableInfo, variant: &VariantName) -> String {
+    if variant.fields.is_empty() {
+        return format!("({}){}", varinfo.name, variant.name);
+    }
+
+    let fields: Vec<String> = variant
+        .fields
+        .iter()
+        .map(|field| format!("{}: {}", field.name, format_variant_field(varinfo, field)))
+        .collect();
+    format!(
+        "({}){} {{ {} }}",
+        varinfo.name,
+        variant.name,
+        fields.join(", ")
+    )
+}
+
+/// Scalar fields are formatted from the loaded enum bytes, others are shown as {...}
+fn format_variant_field(varinfo: &VariableInfo, field: &VariantField) -> String {
+    let layout = field.layout;
+    let bytes = varinfo
+        .memory_slice
+        .memory_slice
+        .get(layout.offset..layout.offset + layout.byte_size);
+    let (encoding, bytes) = match (field.encoding, bytes) {
+        (Some(encoding), Some(bytes)) => (encoding, bytes),
+        _ => return String::from("{...}"),
+    };
+    let tag = if encoding == gimli::DW_ATE_address {
+        gimli::DW_TAG_pointer_type
+    } else {
+        gimli::DW_TAG_base_type
+    };
+
+    let field_info = VariableInfo {
+        byte_size: layout.byte_size,
+        memory_slice: MemorySlice::from_u8_vec(bytes.to_vec()),
+        radix: varinfo.radix,
+        ..VariableInfo::new(field.type_name.clone(), tag, encoding, varinfo.byte_order)
+    };
+    format_object(&field_info).unwrap_or_else(|_| String::from("{...}"))
+}
+
+#[derive(Serialize)]
+struct ValueDescription<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    type_name: &'a str,
+    value: &'a str,
+    encoding: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    children: Option<usize>,
+}
+
+/// Describe an evaluated variable as JSON, value is the display string without the type
+pub fn format_json(varinfo: &VariableInfo, display: &str) -> String {
+    let type_prefix = format!("({})", varinfo.name);
+    let value = display
+        .strip_prefix(&type_prefix)
+        .unwrap_or(display)
+        .trim_start();
+    let encoding = match varinfo.tag {
+        gimli::DW_TAG_base_type
+        | gimli::DW_TAG_enumeration_type
+        | gimli::DW_TAG_pointer_type
+        | gimli::DW_TAG_reference_type => varinfo
+            .encoding
+            .static_string()
+            .map(|name| name.trim_start_matches("DW_ATE_")),
+        _ => None,
+    };
+
+    let description = ValueDescription {
+        name: &varinfo.variable_name,
+        type_name: &varinfo.name,
+        value,
+        encoding,
+        children: varinfo.children,
+    };
+    serde_json::to_string(&description).unwrap_or_default()
+}
+
+/// Decimal representation of a fixed-point value, exact for decimal scales
+fn fixed_value(value: BigInt, scale: Option<FixedScale>) -> String {
+    match scale {
+        None => value.to_string(),
+        Some(FixedScale::Binary(n)) => match i64::try_from(&value) {
+            Ok(v) => format!("{}", v as f64 * 2f64.powi(n as i32)),
+            Err(_) => value.to_string(),
+        },
+        Some(FixedScale::Decimal(n)) if n >= 0 => {
+            format!("{}{}", value, "0".repeat(n as usize))
+        }
+        Some(FixedScale::Decimal(n)) => {
+            let sign = if value < BigInt::from(0) { "-" } else { "" };
+            let digits = value.magnitude().to_string();
+            let fraction_digits = n.unsigned_abs() as usize;
+            let digits = format!("{:0>width$}", digits, width = fraction_digits + 1);
+            let (integer, fraction) = digits.split_at(digits.len() - fraction_digits);
+            format!("{}{}.{}", sign, integer, fraction)
+        }
+    }
+}
+
+/// Decimal representation of a 4 or 8 bytes float
+fn float_value(bytes: &[u8], byte_order: RunTimeEndian) -> Result<String> {
+    match bytes.len() {
+        4 => {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            let value = if byte_order.is_big_endian() {
+                f32::from_be_bytes(bytes)
+            } else {
+                f32::from_le_bytes(bytes)
+            };
+            Ok(format!("{}", value))
+        }
+        8 => {
+            let bytes = [
+                bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6], bytes[7],
+            ];
+            let value = if byte_order.is_big_endian() {
+                f64::from_be_bytes(bytes)
+            } else {
+                f64::from_le_bytes(bytes)
+            };
+            Ok(format!("{}", value))
+        }
+        other => Err(anyhow!("unsupported float size: {}", other)),
+    }
+}
 
 pub fn format_object(varinfo: &VariableInfo) -> Result<String> {
+    if let Some(bit_size) = varinfo.bit_size {
+        return format_bitfield(varinfo, bit_size);
+    }
+
     match varinfo.tag {
         gimli::DW_TAG_base_type => {
             let name = &varinfo.name;
             let byte_size = varinfo.byte_size;
             let encoding = varinfo.encoding;
-            let mut bytes = Vec::new();
-            bytes.extend_from_slice(&varinfo.memory_slice.memory_slice[0..(byte_size as usize)]);
+            let byte_order = varinfo.byte_order;
+            let bytes = loaded_bytes(varinfo, byte_size)?;
 
             match encoding {
                 gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => {
-                    let value = BigInt::from_signed_bytes_le(&bytes);
-                    Ok(format!("({}){}", name, value))
+                    let value = format_integer(signed_from_bytes(bytes, byte_order), varinfo.radix);
+                    if encoding == gimli::DW_ATE_signed_char && byte_size == 1 {
+                        let c = std::ascii::escape_default(bytes[0]);
+                        Ok(format!("({}){} '{}'", name, value, c))
+                    } else {
+                        Ok(format!("({}){}", name, value))
+                    }
                 }
                 gimli::DW_ATE_unsigned | gimli::DW_ATE_unsigned_char => {
-                    let value = BigUint::from_bytes_le(&bytes);
-                    Ok(format!("({}){}", name, value))
+                    let value =
+                        format_integer(unsigned_from_bytes(bytes, byte_order), varinfo.radix);
+                    if encoding == gimli::DW_ATE_unsigned_char && byte_size == 1 {
+                        let c = std::ascii::escape_default(bytes[0]);
+                        Ok(format!("({}){} '{}'", name, value, c))
+                    } else {
+                        Ok(format!("({}){}", name, value))
+                    }
+                }
+                gimli::DW_ATE_UTF => {
+                    let value = unsigned_from_bytes(bytes, byte_order);
+                    let code_point = value.to_u32_digits().first().copied().unwrap_or(0);
+                    match std::char::from_u32(code_point) {
+                        Some(c) => Ok(format!("({})'{}'", name, c.escape_debug())),
+                        None => Ok(format!("({}){}", name, value)),
+                    }
                 }
                 gimli::DW_ATE_boolean => {
-                    let value = match bytes[0] {
-                        0 => false,
-                        _ => true,
+                    let value = match bytes.first() {
+                        Some(0) => false,
+                        Some(_) => true,
+                        None => return Err(anyhow!("expected 1 bytes, got 0")),
                     };
                     Ok(format!("({}){}", name, value))
                 }
-                gimli::DW_ATE_float => match byte_size {
-                    4 => {
-                        let value = f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
-                        Ok(format!("({}){}", name, value))
+                gimli::DW_ATE_float => Ok(format!("({}){}", name, float_value(bytes, byte_order)?)),
+                // real part followed by imaginary part, each a float of half the size
+                gimli::DW_ATE_complex_float => {
+                    let (real, imaginary) = bytes.split_at(byte_size / 2);
+                    let real = float_value(real, byte_order)?;
+                    let imaginary = float_value(imaginary, byte_order)?;
+                    if imaginary.starts_with('-') {
+                        Ok(format!("({}){}{}i", name, real, imaginary))
+                    } else {
+                        Ok(format!("({}){}+{}i", name, real, imaginary))
                     }
-                    8 => {
-                        let value = f64::from_le_bytes([
-                            bytes[0], bytes[1], bytes[2], bytes[3], bytes[4], bytes[5], bytes[6],
-                            bytes[7],
-                        ]);
-                        Ok(format!("({}){}", name, value))
-                    }
-                    _ => unimplemented!(),
-                },
+                }
+                gimli::DW_ATE_signed_fixed | gimli::DW_ATE_unsigned_fixed => {
+                    let value = if encoding == gimli::DW_ATE_signed_fixed {
+                        signed_from_bytes(bytes, byte_order)
+                    } else {
+                        BigInt::from(unsigned_from_bytes(bytes, byte_order))
+                    };
+                    Ok(format!(
+                        "({}){}",
+                        name,
+                        fixed_value(value, varinfo.fixed_scale)
+                    ))
+                }
+                // shown without failing the members and elements around it
+                gimli::DW_ATE_decimal_float => Ok(format!("({})<unsupported decimal float>", name)),
                 other => Err(anyhow!(format!("unsupported attribute type: {}", other))),
             }
         }
+        gimli::DW_TAG_enumeration_type => {
+            let name = &varinfo.name;
+            let bytes = loaded_bytes(varinfo, varinfo.byte_size)?;
+            let value = match varinfo.encoding {
+                gimli::DW_ATE_signed | gimli::DW_ATE_signed_char => {
+                    signed_from_bytes(bytes, varinfo.byte_order)
+                }
+                _ => BigInt::from(unsigned_from_bytes(bytes, varinfo.byte_order)),
+            };
+
+            match varinfo.enumerators.iter().find(|(_, v)| *v == value) {
+                Some((enumerator, _)) => Ok(format!("({}){}", name, enumerator)),
+                None => Ok(format!(
+                    "({}){}",
+                    name,
+                    format_integer(value, varinfo.radix)
+                )),
+            }
+        }
+        gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => {
+            let name = &varinfo.name;
+            let bytes = loaded_bytes(varinfo, varinfo.byte_size)?;
+            let value = unsigned_from_bytes(bytes, varinfo.byte_order);
+
+            if value.bits() == 0 {
+                Ok(format!("({}) 0x0 <null>", name))
+            } else {
+                Ok(format!("({}) 0x{:x}", name, value))
+            }
+        }
         gimli::DW_TAG_class_type | gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type => {
             Ok(varinfo.name.clone())
         }
@@ -0,0 +1,118 @@
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::Deserialize;
+
+/// Renders structs whose type name matches type_pattern
+#[derive(Clone)]
+pub struct PrettyPrinter {
+    pub type_pattern: Regex,
+    pub kind: PrettyPrinterKind,
+}
+
+/// Rendering strategy, deserialized from specs like
+/// `{"kind": "pointer_length_string", "data": "__data", "length": "__size"}`
+#[derive(Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PrettyPrinterKind {
+    /// Text pointed by the data member whose byte count is held by the length member,
+    /// members are dotted paths like "vec.len"
+    PointerLengthString { data: String, length: String },
+}
+
+/// Location of a member inside the loaded struct bytes
+#[derive(Copy, Clone)]
+pub struct MemberLayout {
+    pub offset: usize,
+    pub byte_size: usize,
+}
+
+/// Pretty printer resolved against the debug info of a variable
+#[derive(Clone)]
+pub enum PrettyLayout {
+    String {
+        data: MemberLayout,
+        length: MemberLayout,
+    },
+    /// The text of the string is being read
+    Text { length: usize },
+    /// Rust enum (DW_TAG_variant_part), the variant is chosen by the discriminant member
+    Variant {
+        discriminant: MemberLayout,
+        variants: Vec<VariantName>,
+    },
+}
+
+/// A DW_TAG_variant, discr_value is None for the default variant
+#[derive(Clone)]
+pub struct VariantName {
+    pub discr_value: Option<u64>,
+    pub name: String,
+    pub fields: Vec<VariantField>,
+}
+
+/// A field of a variant, located inside the loaded enum bytes
+#[derive(Clone)]
+pub struct VariantField {
+    pub name: String,
+    pub type_name: String,
+    pub layout: MemberLayout,
+    /// None for fields which aren't scalars, they are shown as {...}
+    pub encoding: Option<gimli::DwAte>,
+}
+
+impl PrettyPrinter {
+    fn string(type_pattern: &str, data: &str, length: &str) -> Self {
+        Self {
+            type_pattern: Regex::new(type_pattern).unwrap(),
+            kind: PrettyPrinterKind::PointerLengthString {
+                data: data.to_string(),
+                length: length.to_string(),
+            },
+        }
+    }
+}
+
+/// Printers registered by the client, consulted before the builtin ones
+#[derive(Default)]
+pub struct PrettyPrinters {
+    custom: Vec<PrettyPrinter>,
+    builtin: Vec<PrettyPrinter>,
+}
+
+impl PrettyPrinters {
+    pub fn register(&mut self, type_pattern: &str, spec_json: &str) -> Result<()> {
+        let type_pattern = Regex::new(type_pattern)?;
+        let kind = serde_json::from_str(spec_json)
+            .map_err(|e| anyhow!("invalid pretty printer spec: {}", e))?;
+
+        self.custom.push(PrettyPrinter { type_pattern, kind });
+        Ok(())
+    }
+
+    pub fn set_builtin_strings(&mut self, enabled: bool) {
+        self.builtin = if enabled {
+            builtin_string_printers()
+        } else {
+            Vec::new()
+        };
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &PrettyPrinter> {
+        self.custom.iter().chain(self.builtin.iter())
+    }
+}
+
+/// Rust String / &str and libstdc++ std::string, the first printer whose members resolve wins
+fn builtin_string_printers() -> Vec<PrettyPrinter> {
+    vec![
+        PrettyPrinter::string("^&str$", "data_ptr", "length"),
+        PrettyPrinter::string("^String$", "vec.buf.inner.ptr.pointer.pointer", "vec.len"),
+        PrettyPrinter::string("^String$", "vec.buf.ptr.pointer.pointer", "vec.len"),
+        PrettyPrinter::string("^String$", "vec.buf.ptr.pointer", "vec.len"),
+        PrettyPrinter::string(
+            "^basic_string<char,",
+            "_M_dataplus._M_p",
+            "_M_string_length",
+        ),
+    ]
+}
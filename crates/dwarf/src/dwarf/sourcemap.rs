@@ -1,14 +1,20 @@
 use anyhow::{anyhow, Result};
-use gimli::{DebugLine, DebugLineOffset, DebuggingInformationEntry, LineRow, Reader, Unit};
+use gimli::{DebugLine, DebuggingInformationEntry, LineRow, Reader, Unit};
 
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashMap};
+use std::ops::Range;
 use std::path::{Path, PathBuf};
+use wasm_bindgen::prelude::*;
 
 use super::utils::{
-    clone_string_attribute, convert_from_windows_stype_path, is_absolute_path, normalize_path,
+    clone_string_attribute, convert_from_windows_stype_path, is_absolute_path, is_windows_path,
+    normalize_path,
 };
-use super::{DwarfDebugData, DwarfReader, DwarfReaderOffset};
+use super::{DwarfReader, DwarfReaderOffset};
+
+/// Rows of a source file grouped by line, sorted by line
+type LineRows = Vec<(u64, Vec<LineRow>)>;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ColumnType {
@@ -16,11 +22,23 @@ pub enum ColumnType {
     Column(u64),
 }
 
+/// How requested source paths are matched against the paths recorded in DWARF
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PathMatchMode {
+    Exact,
+    /// Fall back to the longest common suffix of path components,
+    /// for binaries built on another machine
+    Suffix,
+}
+
 #[derive(Clone)]
 pub struct LineInfo {
     pub filepath: String,
     pub line: Option<u64>,
     pub column: ColumnType,
+    /// The row is a recommended breakpoint location (a statement boundary)
+    pub is_stmt: bool,
 }
 
 pub fn transform_debug_line(
@@ -42,17 +60,18 @@ pub fn transform_debug_line(
 
     let header = program.header();
 
-    let sequence_base_index: usize;
     let mut dirs = vec![];
     let mut files = Vec::new();
     let mut file_sorted_rows = BTreeMap::new();
 
-    if header.version() <= 4 {
+    // DWARF 4 numbers files from 1, leaving index 0 to the compilation unit's source.
+    // DWARF 5 lists the primary source file itself at index 0.
+    let sequence_base_index: usize = if header.version() <= 4 {
         dirs.push("./".to_string());
-        sequence_base_index = 1;
+        1
     } else {
-        sequence_base_index = 0;
-    }
+        0
+    };
 
     for dir in header.include_directories() {
         dirs.push(clone_string_attribute(dwarf, unit, dir.clone()).expect("parsable dir string"));
@@ -81,8 +100,11 @@ pub fn transform_debug_line(
     }
 
     for (file_index, file_entry) in header.file_names().iter().enumerate() {
-        let dir = &dirs[file_entry.directory_index() as usize];
-        let dir = convert_from_windows_stype_path(dir);
+        let dir = dirs
+            .get(file_entry.directory_index() as usize)
+            .cloned()
+            .unwrap_or_default();
+        let dir = convert_from_windows_stype_path(&dir);
 
         let dir_path = Path::new(&dir);
         let path = clone_string_attribute(dwarf, unit, file_entry.path_name())?;
@@ -96,32 +118,39 @@ pub fn transform_debug_line(
             }
         }
 
-        files.push(PathBuf::from(&normalize_path(
-            &path.to_string_lossy().into_owned(),
-        )));
+        files.push(PathBuf::from(&normalize_path(&path.to_string_lossy())));
         file_sorted_rows.insert(file_index + sequence_base_index, BTreeMap::new());
     }
 
     let mut rows = program.rows();
     let mut sorted_rows = BTreeMap::new();
+    let mut sequences = Vec::new();
+    let mut sequence_start = None;
     while let Some((_, row)) = rows.next_row()? {
+        // the terminator's address is past the sequence, not the start of another line
+        if row.end_sequence() {
+            if let Some(start) = sequence_start.take() {
+                sequences.push(start..row.address());
+            }
+            continue;
+        }
+        if sequence_start.is_none() {
+            sequence_start = Some(row.address());
+        }
+
+        // of the rows sharing an address, the last one describes the instruction
         sorted_rows.insert(row.address(), *row);
 
-        match file_sorted_rows.get_mut(&(row.file_index() as usize)) {
-            Some(x) => {
-                x.insert(
-                    match row.line() {
-                        Some(x) => x.get(),
-                        None => 0,
-                    },
-                    *row,
-                );
-            }
-            None => {}
+        if let Some(x) = file_sorted_rows.get_mut(&(row.file_index() as usize)) {
+            let line = match row.line() {
+                Some(x) => x.get(),
+                None => 0,
+            };
+            x.entry(line).or_insert_with(Vec::new).push(*row);
         }
     }
     let sorted_rows: Vec<_> = sorted_rows.into_iter().collect();
-    let mapped_file_sorted_rows: Vec<(usize, Vec<(u64, LineRow)>)> = file_sorted_rows
+    let mapped_file_sorted_rows: Vec<(usize, LineRows)> = file_sorted_rows
         .into_iter()
         .map(|x| (x.0, x.1.into_iter().collect()))
         .collect();
@@ -129,28 +158,70 @@ pub fn transform_debug_line(
         address_sorted_rows: sorted_rows,
         file_sorted_rows: mapped_file_sorted_rows,
         paths: files,
+        sequences,
     })
 }
 
+/// Resolve a file index of the unit's line program (e.g. DW_AT_decl_file) into a path
+pub(crate) fn file_index_path(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader, DwarfReaderOffset>,
+    file_index: u64,
+) -> Result<Option<String>> {
+    let header = match unit.line_program {
+        Some(ref program) => program.header(),
+        None => return Ok(None),
+    };
+    let file_entry = match header.file(file_index) {
+        Some(x) => x,
+        None => return Ok(None),
+    };
+
+    let dir = match file_entry.directory(header) {
+        Some(attr) => clone_string_attribute(dwarf, unit, attr)?,
+        None => String::from(""),
+    };
+    let dir = convert_from_windows_stype_path(&dir);
+
+    let path = clone_string_attribute(dwarf, unit, file_entry.path_name())?;
+    let mut path = Path::new(&dir).join(convert_from_windows_stype_path(&path));
+
+    if !is_absolute_path(path.to_str().unwrap_or_default()) {
+        if let Some(ref comp_dir) = unit.comp_dir {
+            let comp_dir = String::from_utf8(comp_dir.to_slice()?.to_vec()).unwrap_or_default();
+            let comp_dir = convert_from_windows_stype_path(&comp_dir);
+            path = Path::new(&comp_dir).join(path);
+        }
+    }
+
+    Ok(Some(normalize_path(&path.to_string_lossy())))
+}
+
 pub struct DwarfUnitSourceMap {
     address_sorted_rows: Vec<(u64, LineRow)>,
-    file_sorted_rows: Vec<(usize, Vec<(u64, LineRow)>)>,
+    file_sorted_rows: Vec<(usize, LineRows)>,
     paths: Vec<std::path::PathBuf>,
+    /// [start, end) addresses of each sequence of the line program
+    sequences: Vec<Range<u64>>,
+}
+
+fn row_column(row: &LineRow) -> ColumnType {
+    match row.column() {
+        gimli::ColumnType::Column(c) => ColumnType::Column(c.get()),
+        gimli::ColumnType::LeftEdge => ColumnType::LeftEdge,
+    }
 }
 
-fn transform_lineinfo(row: &LineRow, paths: &Vec<std::path::PathBuf>) -> LineInfo {
-    let filepath = paths[row.file_index() as usize].clone();
+fn transform_lineinfo(row: &LineRow, paths: &[std::path::PathBuf]) -> LineInfo {
     LineInfo {
-        filepath: filepath.to_str().unwrap_or_default().to_string(),
-        line: if let Some(x) = row.line() { Some(x.get()) } else { None },
-        column: match row.column() {
-            gimli::ColumnType::Column(c) => ColumnType::Column(c.get()),
-            gimli::ColumnType::LeftEdge => ColumnType::LeftEdge,
-        },
+        filepath: transform_file_index(row.file_index() as usize, paths),
+        line: row.line().map(|x| x.get()),
+        column: row_column(row),
+        is_stmt: row.is_stmt(),
     }
 }
-fn transform_file_index(file_index: usize, paths: &Vec<std::path::PathBuf>) -> String {
-    match paths.get(file_index as usize) {
+fn transform_file_index(file_index: usize, paths: &[std::path::PathBuf]) -> String {
+    match paths.get(file_index) {
         Some(x) => match x.clone().to_str() {
             Some(x) => x.to_string(),
             None => String::from("??? (stringify failed)"),
@@ -159,25 +230,49 @@ fn transform_file_index(file_index: usize, paths: &Vec<std::path::PathBuf>) -> S
     }
 }
 
+/// Replace the longest leading directory of the path found in the (directory,
+/// replacement) pairs, matching whole path components only
+fn map_leading_directory<'a>(
+    path: String,
+    mappings: impl Iterator<Item = (&'a String, &'a String)>,
+) -> String {
+    let mut mappings: Vec<(&String, &String)> = mappings.collect();
+    mappings.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(b.0)));
+
+    for (directory, replacement) in mappings {
+        let rest = match path.strip_prefix(directory.as_str()) {
+            Some(rest) => rest,
+            None => continue,
+        };
+        if rest.is_empty() || rest.starts_with('/') || directory.ends_with('/') {
+            return format!("{}{}", replacement, rest);
+        }
+    }
+    path
+}
+
 pub struct DwarfSourceMap {
-    /// Source files -> DebugLineOffsets mapping table
-    file_sorted_entry_offsets: Vec<(String, DebugLineOffset)>,
     /// Source files -> LineRows by source lines mapping table
-    file_sorted_rows: Vec<(String, Vec<(u64, LineRow)>)>,
+    file_sorted_rows: Vec<(String, LineRows)>,
     /// Code address -> Source files mapping table
     address_sorted_rows: Vec<(u64, LineInfo)>,
+    /// [start, end) addresses of the line sequences of every unit, sorted by start
+    sequences: Vec<Range<u64>>,
 
     directory_map: RefCell<HashMap<String, String>>,
-
-    dwarf_data: DwarfDebugData,
+    /// Normalized file path -> index into file_sorted_rows, cleared when the directory map changes
+    file_lookup_cache: RefCell<HashMap<String, Option<usize>>>,
+    path_match_mode: PathMatchMode,
 }
 
 impl DwarfSourceMap {
-    pub fn new(units: Vec<DwarfUnitSourceMap>, dwarf_data: DwarfDebugData) -> Self {
+    pub fn new(units: Vec<DwarfUnitSourceMap>) -> Self {
         let mut address_rows = BTreeMap::new();
         let mut file_rows = BTreeMap::new();
-        for unit in units {
+        let mut sequences = Vec::new();
+        for mut unit in units {
             let path = unit.paths;
+            sequences.append(&mut unit.sequences);
 
             for (addr, row) in &unit.address_sorted_rows {
                 let line_info = transform_lineinfo(row, &path);
@@ -185,66 +280,475 @@ impl DwarfSourceMap {
             }
             for (file_index, vec) in unit.file_sorted_rows {
                 let file_name = transform_file_index(file_index, &path);
-                file_rows.insert(file_name, vec);
+                // headers and templates may be shared by several units
+                let line_rows = file_rows.entry(file_name).or_insert_with(BTreeMap::new);
+                for (line, mut rows) in vec {
+                    line_rows
+                        .entry(line)
+                        .or_insert_with(Vec::new)
+                        .append(&mut rows);
+                }
             }
         }
+        sequences.sort_unstable_by_key(|sequence| (sequence.start, sequence.end));
         Self {
-            file_sorted_entry_offsets: Vec::new(),
             address_sorted_rows: address_rows.into_iter().collect(),
-            file_sorted_rows: file_rows.into_iter().collect(),
+            sequences,
+            file_sorted_rows: file_rows
+                .into_iter()
+                .map(|(file, rows)| (file, rows.into_iter().collect()))
+                .collect(),
             directory_map: RefCell::new(HashMap::new()),
-            dwarf_data,
+            file_lookup_cache: RefCell::new(HashMap::new()),
+            path_match_mode: PathMatchMode::Exact,
         }
     }
 
     pub fn set_directory_map(&self, from: String, to: String) {
         self.directory_map.borrow_mut().insert(from, to);
+        self.file_lookup_cache.borrow_mut().clear();
+    }
+
+    pub fn set_path_match_mode(&mut self, mode: PathMatchMode) {
+        self.path_match_mode = mode;
+        self.file_lookup_cache.borrow_mut().clear();
+    }
+
+    /// Whether the offset lies inside one of the sequences of the line tables
+    pub fn covers(&self, offset: usize) -> bool {
+        self.sequence_containing(offset as u64).is_some()
+    }
+
+    /// The sequence whose [start, end) range contains the address. Sequences of
+    /// functions removed by the linker may overlap others, e.g. all starting at 0
+    fn sequence_containing(&self, address: u64) -> Option<&Range<u64>> {
+        let upper = self
+            .sequences
+            .partition_point(|sequence| sequence.start <= address);
+        self.sequences[..upper]
+            .iter()
+            .rev()
+            .find(|sequence| sequence.contains(&address))
     }
 
-    fn update_file_sorted_rows(&mut self, _offset: DebugLineOffset) {}
+    /// (address, line) of the rows inside the ranges, e.g. of a function for step ranges
+    pub fn line_rows_in_ranges(&self, ranges: &[std::ops::Range<u64>]) -> Vec<(usize, u32)> {
+        let mut rows = Vec::new();
+        for range in ranges {
+            let first = match self
+                .address_sorted_rows
+                .binary_search_by_key(&range.start, |i| i.0)
+            {
+                Ok(i) => i,
+                Err(i) => i,
+            };
+            rows.extend(
+                self.address_sorted_rows[first..]
+                    .iter()
+                    .take_while(|(address, _)| *address < range.end)
+                    .map(|(address, line_info)| {
+                        (*address as usize, line_info.line.unwrap_or(0) as u32)
+                    }),
+            );
+        }
+        rows.sort_unstable();
+        rows
+    }
 
+    /// Line and column of the row with the greatest address not above offset
     pub fn find_line_info(&self, offset: usize) -> Option<LineInfo> {
-        let mut line_info = match self
+        self.find_line_info_where(offset, false)
+    }
+
+    /// [start, end) addresses of the row containing offset. The end is the address of
+    /// the following row, or the end of the sequence for the last row of a sequence
+    pub fn find_line_range(&self, offset: usize) -> Option<(usize, usize)> {
+        let sequence_end = self.sequence_containing(offset as u64)?.end;
+        let index = match self
             .address_sorted_rows
             .binary_search_by_key(&(offset as u64), |i| i.0)
         {
-            Ok(i) => self.address_sorted_rows[i].1.clone(),
-            Err(i) => {
-                if i > 0 {
-                    self.address_sorted_rows[i - 1].1.clone()
-                } else {
-                    return None;
-                }
-            }
+            Ok(i) => i,
+            Err(0) => return None,
+            Err(i) => i - 1,
         };
-        for (from, to) in self.directory_map.borrow().iter() {
-            line_info.filepath = line_info.filepath.replace(from, to);
-        }
+        let start = self.address_sorted_rows[index].0;
+        let end = match self.address_sorted_rows.get(index + 1) {
+            Some((address, _)) if *address < sequence_end => *address,
+            _ => sequence_end,
+        };
+        Some((start as usize, end as usize))
+    }
+
+    /// Same as find_line_info, considering only rows at statement boundaries
+    pub fn find_statement_line_info(&self, offset: usize) -> Option<LineInfo> {
+        self.find_line_info_where(offset, true)
+    }
+
+    /// Rows before the sequence containing offset belong to other code, and addresses
+    /// between sequences have no line at all
+    fn find_line_info_where(&self, offset: usize, statements_only: bool) -> Option<LineInfo> {
+        let sequence = self.sequence_containing(offset as u64)?;
+        let start = self
+            .address_sorted_rows
+            .partition_point(|(address, _)| *address < sequence.start);
+        let end = self
+            .address_sorted_rows
+            .partition_point(|(address, _)| *address <= offset as u64);
+        let mut line_info = self.address_sorted_rows[start..end]
+            .iter()
+            .rev()
+            .map(|(_, line_info)| line_info)
+            .find(|line_info| !statements_only || line_info.is_stmt)?
+            .clone();
+        line_info.filepath = self.apply_directory_map(line_info.filepath);
         Some(line_info)
     }
 
+    /// Source files referred by the line tables, with the directory map applied
+    pub fn source_files(&self) -> Vec<String> {
+        let mut files: Vec<String> = self
+            .file_sorted_rows
+            .iter()
+            .map(|(file, _)| self.apply_directory_map(file.clone()))
+            .collect();
+        files.sort();
+        files.dedup();
+        files
+    }
+
+    /// Map a path recorded in DWARF to the remapped (on-disk) path. Only leading
+    /// directories are mapped, the longest directory matching first
+    fn apply_directory_map(&self, path: String) -> String {
+        map_leading_directory(path, self.directory_map.borrow().iter())
+    }
+
+    /// Map a remapped (on-disk) path back to the path recorded in DWARF, the inverse of
+    /// apply_directory_map
+    fn reverse_directory_map(&self, path: String) -> String {
+        let directory_map = self.directory_map.borrow();
+        let mappings = directory_map.iter().map(|(from, to)| (to, from));
+        map_leading_directory(path, mappings)
+    }
+
     pub fn find_address(&self, file: &LineInfo) -> Option<usize> {
-        let escaped_filename = convert_from_windows_stype_path(&file.filepath);
+        self.find_all_addresses(file).first().copied()
+    }
+
+    /// Line a breakpoint on the source line binds to, the first line at or after it
+    /// which has code, and the address of the breakpoint
+    pub fn resolve_breakpoint_line(&self, file: &LineInfo) -> Option<(u32, usize)> {
+        let line_vec = &self.file_sorted_rows[self.find_file_rows(&file.filepath)?].1;
+        let first = match line_vec.binary_search_by_key(&file.line.unwrap_or_default(), |i| i.0) {
+            Ok(i) => i,
+            Err(i) => i,
+        };
+        let (line, _) = line_vec.get(first)?;
+
+        let resolved = LineInfo {
+            filepath: file.filepath.clone(),
+            line: Some(*line),
+            column: ColumnType::LeftEdge,
+            is_stmt: true,
+        };
+        let address = match self.find_statement_addresses(&resolved).first() {
+            Some(x) => *x,
+            None => self.find_address(&resolved)?,
+        };
+        Some((*line as u32, address))
+    }
+
+    /// All code addresses of the source line, rows with prologue_end first
+    pub fn find_all_addresses(&self, file: &LineInfo) -> Vec<usize> {
+        self.find_addresses_where(file, false)
+    }
+
+    /// Same as find_all_addresses, considering only rows at statement boundaries
+    pub fn find_statement_addresses(&self, file: &LineInfo) -> Vec<usize> {
+        self.find_addresses_where(file, true)
+    }
+
+    /// Index into file_sorted_rows of the source file, memoized per normalized path so
+    /// spellings of the same file share an entry
+    fn find_file_rows(&self, filepath: &str) -> Option<usize> {
+        let escaped_filename = self.reverse_directory_map(filepath.to_string());
+        let escaped_filename = convert_from_windows_stype_path(&escaped_filename);
         let escaped_filename = normalize_path(&escaped_filename);
-        let line_vec = match self
+        if let Some(index) = self.file_lookup_cache.borrow().get(&escaped_filename) {
+            return *index;
+        }
+
+        let index = match self
             .file_sorted_rows
             .binary_search_by(|i| i.0.cmp(&escaped_filename))
         {
-            Ok(i) => &self.file_sorted_rows[i].1,
-            Err(_) => {
-                return None;
+            Ok(i) => Some(i),
+            Err(_) if is_windows_path(&escaped_filename) => {
+                let key = escaped_filename.to_lowercase();
+                self.file_sorted_rows
+                    .iter()
+                    .position(|i| is_windows_path(&i.0) && i.0.to_lowercase() == key)
+            }
+            Err(_) => None,
+        };
+        let index = match (index, self.path_match_mode) {
+            (None, PathMatchMode::Suffix) => self.find_file_rows_by_suffix(&escaped_filename),
+            (index, _) => index,
+        };
+
+        self.file_lookup_cache
+            .borrow_mut()
+            .insert(escaped_filename, index);
+        index
+    }
+
+    /// Index of the file sharing the longest suffix of path components with the path.
+    /// Among equally long matches the shortest path wins, None if that is ambiguous
+    fn find_file_rows_by_suffix(&self, path: &str) -> Option<usize> {
+        let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+        let mut best: Option<(usize, (usize, usize))> = None;
+        let mut ambiguous = false;
+
+        for (index, (file, _)) in self.file_sorted_rows.iter().enumerate() {
+            let file_components: Vec<&str> = file.split('/').filter(|c| !c.is_empty()).collect();
+            let common = components
+                .iter()
+                .rev()
+                .zip(file_components.iter().rev())
+                .take_while(|(a, b)| a == b)
+                .count();
+            if common == 0 {
+                continue;
             }
+
+            // longer common suffix first, then fewer components
+            let rank = (common, usize::MAX - file_components.len());
+            match best {
+                Some((_, best_rank)) if rank < best_rank => {}
+                Some((_, best_rank)) if rank == best_rank => ambiguous = true,
+                _ => {
+                    best = Some((index, rank));
+                    ambiguous = false;
+                }
+            }
+        }
+
+        match best {
+            Some((index, _)) if !ambiguous => Some(index),
+            _ => None,
+        }
+    }
+
+    fn find_addresses_where(&self, file: &LineInfo, statements_only: bool) -> Vec<usize> {
+        let line_vec = match self.find_file_rows(&file.filepath) {
+            Some(i) => &self.file_sorted_rows[i].1,
+            None => return Vec::new(),
         };
 
-        match line_vec.binary_search_by_key(&file.line.unwrap_or_default(), |i| i.0) {
-            Ok(i) => Some(line_vec[i].1.address() as usize),
+        let rows = match line_vec.binary_search_by_key(&file.line.unwrap_or_default(), |i| i.0) {
+            Ok(i) => &line_vec[i].1,
             Err(i) => {
                 if i > 0 {
-                    Some(line_vec[i - 1].1.address() as usize)
+                    &line_vec[i - 1].1
                 } else {
-                    None
+                    return Vec::new();
                 }
             }
+        };
+
+        let mut rows: Vec<&LineRow> = rows
+            .iter()
+            .filter(|row| !statements_only || row.is_stmt())
+            .collect();
+
+        // breakpoints after the prologue stop once the frame has been set up
+        rows.sort_by_key(|row| (!row.prologue_end(), row.address()));
+
+        let mut addresses = Vec::new();
+        for row in rows {
+            let address = row.address() as usize;
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+        }
+        addresses
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dwarf::test_utils::{debug_data, first_unit, line_table, load_dwarf, Sequence};
+    use crate::dwarf::transform_dwarf_data;
+    use gimli::RunTimeEndian;
+
+    /// Source map of a DWARF 4 unit of main.c whose line program has the given sequences
+    fn source_map(sequences: &[Sequence]) -> DwarfSourceMap {
+        let sections = line_table(4, "main.c", sequences);
+        transform_dwarf_data(debug_data(&sections))
+            .unwrap()
+            .sourcemap
+    }
+
+    #[test]
+    fn covers_only_addresses_inside_a_sequence() {
+        let map = source_map(&[
+            (0x10, &[(0, 1, 0), (4, 2, 0)], 0x10),
+            (0x40, &[(0, 5, 0)], 0x8),
+            // a function removed by the linker, left at address 0
+            (0, &[(0, 9, 0)], 0x4),
+        ]);
+
+        for covered in [0x0, 0x3, 0x10, 0x14, 0x1f, 0x40, 0x47] {
+            assert!(map.covers(covered), "0x{:x} is covered", covered);
         }
+        for outside in [0x4, 0xf, 0x20, 0x30, 0x48, 0x100] {
+            assert!(!map.covers(outside), "0x{:x} is not covered", outside);
+        }
+    }
+    #[test]
+    fn line_ranges_end_at_the_next_row_or_the_sequence_end() {
+        let map = source_map(&[
+            (0x10, &[(0, 1, 0), (4, 2, 0)], 0x10),
+            (0x40, &[(0, 5, 0)], 0x8),
+        ]);
+
+        assert_eq!(map.find_line_range(0x12), Some((0x10, 0x14)));
+        assert_eq!(map.find_line_range(0x14), Some((0x14, 0x20)));
+        assert_eq!(map.find_line_range(0x1f), Some((0x14, 0x20)));
+        assert_eq!(map.find_line_range(0x44), Some((0x40, 0x48)));
+        // between the sequences there is no row
+        assert_eq!(map.find_line_range(0x30), None);
+        assert_eq!(map.find_line_range(0x48), None);
+    }
+
+    #[test]
+    fn dwarf5_file_indices_start_at_the_primary_source() {
+        let sections = line_table(5, "util.h", &[(0x10, &[(0, 3, 0)], 0x4)]);
+
+        // DWARF 5 lists main.c itself at index 0, util.h follows at index 1
+        let dwarf = load_dwarf(&sections, RunTimeEndian::Little);
+        let unit = first_unit(&dwarf);
+        let paths: Vec<_> = (0..3)
+            .map(|index| file_index_path(&dwarf, &unit, index).unwrap())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                Some(String::from("/src/main.c")),
+                Some(String::from("/src/util.h")),
+                None
+            ]
+        );
+
+        let map = transform_dwarf_data(debug_data(&sections))
+            .unwrap()
+            .sourcemap;
+        let line_info = map.find_line_info(0x12).unwrap();
+        assert_eq!(line_info.filepath, "/src/util.h");
+        assert_eq!(line_info.line, Some(3));
+        assert_eq!(map.source_files(), vec!["/src/main.c", "/src/util.h"]);
+    }
+
+    #[test]
+    fn remapped_paths_map_back_to_their_longest_directory() {
+        let map = source_map(&[(0x10, &[(0, 1, 0), (4, 2, 0)], 0x10)]);
+        map.set_directory_map(String::from("/src"), String::from("/home/me/work"));
+        map.set_directory_map(String::from("/other"), String::from("/home/me"));
+
+        let line_info = map.find_line_info(0x14).unwrap();
+        assert_eq!(line_info.filepath, "/home/me/work/main.c");
+        assert_eq!(map.find_address(&line_info), Some(0x14));
+
+        let unmapped = LineInfo {
+            filepath: String::from("/home/me/workshop/main.c"),
+            ..line_info
+        };
+        assert_eq!(map.find_address(&unmapped), None);
+    }
+
+    #[test]
+    fn spellings_of_a_path_share_their_lookup() {
+        let map = source_map(&[(0x10, &[(0, 1, 0), (4, 2, 0)], 0x10)]);
+
+        for filepath in ["/src/main.c", "/src/./main.c", "/src/lib/../main.c"] {
+            let line_info = LineInfo {
+                filepath: String::from(filepath),
+                line: Some(2),
+                column: ColumnType::LeftEdge,
+                is_stmt: true,
+            };
+            assert_eq!(map.find_address(&line_info), Some(0x14), "{}", filepath);
+        }
+        let cache = map.file_lookup_cache.borrow();
+        assert_eq!(cache.keys().collect::<Vec<_>>(), vec!["/src/main.c"]);
+    }
+
+    #[test]
+    fn directories_map_only_as_leading_components() {
+        let map = source_map(&[(0x10, &[(0, 1, 0)], 0x10)]);
+        map.set_directory_map(String::from("/src"), String::from("/home/me/work"));
+        map.set_directory_map(String::from("/"), String::from("/mnt/"));
+
+        // the longest directory wins over the root, which maps every other path
+        assert_eq!(
+            map.apply_directory_map(String::from("/src/main.c")),
+            "/home/me/work/main.c"
+        );
+        assert_eq!(
+            map.apply_directory_map(String::from("/srcs/main.c")),
+            "/mnt/srcs/main.c"
+        );
+        // directories in the middle of the path are kept
+        assert_eq!(
+            map.apply_directory_map(String::from("/lib/src/util.c")),
+            "/mnt/lib/src/util.c"
+        );
+        assert_eq!(map.apply_directory_map(String::from("util.c")), "util.c");
+    }
+
+    #[test]
+    fn line_info_comes_from_the_row_of_the_address() {
+        // two statements on line 3, then another function after a gap
+        let map = source_map(&[
+            (0x10, &[(0, 3, 5), (4, 3, 12)], 0x8),
+            (0x40, &[(0, 7, 1)], 0x4),
+        ]);
+
+        let columns: Vec<_> = [0x10, 0x13, 0x14, 0x17]
+            .iter()
+            .map(|offset| {
+                map.find_line_info(*offset)
+                    .map(|info| (info.line, info.column))
+            })
+            .collect();
+        assert_eq!(
+            columns,
+            vec![
+                Some((Some(3), ColumnType::Column(5))),
+                Some((Some(3), ColumnType::Column(5))),
+                Some((Some(3), ColumnType::Column(12))),
+                Some((Some(3), ColumnType::Column(12))),
+            ]
+        );
+        // past the end of the first function the last row doesn't apply
+        assert!(map.find_line_info(0x18).is_none());
+        assert!(map.find_line_info(0x3f).is_none());
+        assert_eq!(map.find_line_info(0x42).unwrap().line, Some(7));
+    }
+
+    #[test]
+    fn sequence_terminators_are_not_rows() {
+        // the terminator of the first function is at the start of the second
+        let map = source_map(&[(0x20, &[(0, 10, 0)], 0x4), (0x10, &[(0, 1, 0)], 0x10)]);
+
+        assert_eq!(map.find_line_info(0x20).unwrap().line, Some(10));
+        let line_1 = LineInfo {
+            filepath: String::from("/src/main.c"),
+            line: Some(1),
+            column: ColumnType::LeftEdge,
+            is_stmt: true,
+        };
+        assert_eq!(map.find_all_addresses(&line_1), vec![0x10]);
     }
 }
@@ -1,12 +1,18 @@
 use anyhow::{anyhow, Result};
-use gimli::{AttributeValue, Unit, UnitOffset, UnitSectionOffset};
+use gimli::{AttributeValue, DebuggingInformationEntry, Unit, UnitOffset, UnitSectionOffset};
+use std::cell::RefCell;
+use std::ops::Range;
 
-use super::utils::clone_string_attribute;
+use super::pretty_printer::PrettyPrinters;
+use super::sourcemap::file_index_path;
+use super::utils::{clone_string_attribute, error};
 use super::variables::{
-    evaluate_variable_from_string, variables_in_unit_entry, FrameBase, TypeDescripter, VariableName,
+    evaluate_variable_from_string, variable_names, variables_in_unit_entry, FrameBase, LazyGroups,
+    VariableName, WasmFrameValues,
 };
 use super::wasm_bindings::{Value, WasmValueVector};
-use super::{unit_type_name, DwarfDebugData, DwarfReader, DwarfReaderOffset, VariableInfo};
+use super::{DwarfDebugData, DwarfReader, DwarfReaderOffset, VariableInfo};
+use crate::console_log;
 
 #[derive(Clone)]
 pub enum WasmLoc {
@@ -22,13 +28,18 @@ enum DwAtWasm {
 
 pub struct Subroutine {
     pub name: Option<String>,
-    pub pc: std::ops::Range<u64>,
+    pub pc: Vec<Range<u64>>,
     pub unit_offset: gimli::UnitSectionOffset,
     pub entry_offset: UnitOffset<DwarfReaderOffset>,
-    pub encoding: gimli::Encoding,
     pub frame_base: Option<WasmLoc>,
 }
 
+pub struct InlinedSubroutine {
+    pub name: Option<String>,
+    pub call_file: Option<String>,
+    pub call_line: Option<u64>,
+}
+
 pub fn transform_subprogram(
     dwarf: &gimli::Dwarf<DwarfReader>,
     unit: &Unit<DwarfReader, DwarfReaderOffset>,
@@ -79,50 +90,193 @@ pub fn read_subprogram_header(
         _ => return Ok(None),
     };
 
-    let name = match node.entry().attr_value(gimli::DW_AT_name)? {
+    let name = match origin_attr_value(unit, node.entry(), gimli::DW_AT_name)? {
         Some(attr) => Some(clone_string_attribute(dwarf, unit, attr)?),
         None => None,
     };
 
-    let low_pc_attr = node.entry().attr_value(gimli::DW_AT_low_pc)?;
-    let high_pc_attr = node.entry().attr_value(gimli::DW_AT_high_pc)?;
-    let frame_base_attr = node.entry().attr_value(gimli::DW_AT_frame_base)?;
+    let frame_base_attr = origin_attr_value(unit, node.entry(), gimli::DW_AT_frame_base)?;
+    let pc = entry_ranges(dwarf, unit, node.entry())?;
+
+    if pc.is_empty() {
+        return Ok(None);
+    }
+
+    let frame_base = if let Some(attr) = frame_base_attr {
+        Some(read_wasm_location(attr)?)
+    } else {
+        None
+    };
+
+    Ok(Some(Subroutine {
+        pc,
+        name,
+        unit_offset,
+        entry_offset: node.entry().offset(),
+        frame_base,
+    }))
+}
+
+/// Collect the non-empty address ranges referred by DW_AT_ranges
+pub(crate) fn read_ranges(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader, DwarfReaderOffset>,
+    attr: AttributeValue<DwarfReader>,
+) -> Result<Vec<Range<u64>>> {
+    let offset = match dwarf.attr_ranges_offset(unit, attr)? {
+        Some(offset) => offset,
+        None => return Ok(Vec::new()),
+    };
+
+    let mut ranges = Vec::new();
+    let mut iter = dwarf.ranges(unit, offset)?;
+    while let Some(range) = iter.next()? {
+        if range.begin < range.end {
+            ranges.push(range.begin..range.end);
+        }
+    }
+    Ok(ranges)
+}
+
+/// Address ranges covered by an entry, from DW_AT_low_pc/DW_AT_high_pc or DW_AT_ranges
+pub(crate) fn entry_ranges(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader, DwarfReaderOffset>,
+    entry: &DebuggingInformationEntry<DwarfReader>,
+) -> Result<Vec<Range<u64>>> {
+    let low_pc = match entry.attr_value(gimli::DW_AT_low_pc)? {
+        Some(attr) => dwarf.attr_address(unit, attr)?,
+        None => None,
+    };
 
-    let subroutine = if let Some(AttributeValue::Addr(low_pc)) = low_pc_attr {
-        let high_pc = match high_pc_attr {
-            Some(AttributeValue::Udata(size)) => low_pc + size,
+    if let Some(low_pc) = low_pc {
+        // high_pc is either an address or, in any constant form, an offset from low_pc
+        let high_pc = match entry.attr_value(gimli::DW_AT_high_pc)? {
             Some(AttributeValue::Addr(high_pc)) => high_pc,
-            Some(x) => unreachable!("high_pc can't be {:?}", x),
-            None => return Ok(None),
+            Some(attr) => match attr.udata_value() {
+                Some(size) => low_pc + size,
+                None => {
+                    console_log!("entry skipped, high_pc can't be {:?}", attr);
+                    return Ok(Vec::new());
+                }
+            },
+            None => return Ok(Vec::new()),
         };
 
-        let size = high_pc - low_pc;
+        let mut ranges = Vec::new();
+        if low_pc < high_pc {
+            ranges.push(low_pc..high_pc);
+        }
+        return Ok(ranges);
+    }
+
+    match entry.attr_value(gimli::DW_AT_ranges)? {
+        Some(attr) => read_ranges(dwarf, unit, attr),
+        None => Ok(Vec::new()),
+    }
+}
+
+fn inlined_subroutines_rec(
+    node: gimli::EntriesTreeNode<DwarfReader>,
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader, DwarfReaderOffset>,
+    code_offset: u64,
+    out_subroutines: &mut Vec<InlinedSubroutine>,
+) -> Result<()> {
+    let mut children = node.children();
+    while let Some(child) = children.next()? {
+        match child.entry().tag() {
+            gimli::DW_TAG_inlined_subroutine => {
+                let ranges = entry_ranges(dwarf, unit, child.entry())?;
+                if !ranges.iter().any(|range| range.contains(&code_offset)) {
+                    continue;
+                }
+
+                let name = match origin_attr_value(unit, child.entry(), gimli::DW_AT_name)? {
+                    Some(attr) => Some(clone_string_attribute(dwarf, unit, attr)?),
+                    None => None,
+                };
+                let call_file = match child.entry().attr_value(gimli::DW_AT_call_file)? {
+                    Some(AttributeValue::FileIndex(index)) => file_index_path(dwarf, unit, index)?,
+                    Some(attr) => match attr.udata_value() {
+                        Some(index) => file_index_path(dwarf, unit, index)?,
+                        None => None,
+                    },
+                    None => None,
+                };
+                let call_line = child
+                    .entry()
+                    .attr_value(gimli::DW_AT_call_line)?
+                    .and_then(|attr| attr.udata_value());
 
-        if size <= 0 {
-            return Ok(None);
+                out_subroutines.push(InlinedSubroutine {
+                    name,
+                    call_file,
+                    call_line,
+                });
+                inlined_subroutines_rec(child, dwarf, unit, code_offset, out_subroutines)?;
+            }
+            gimli::DW_TAG_lexical_block => {
+                inlined_subroutines_rec(child, dwarf, unit, code_offset, out_subroutines)?;
+            }
+            _ => continue,
         }
+    }
+    Ok(())
+}
+
+/// Read an attribute of the entry, falling back to the entries referred by
+/// DW_AT_abstract_origin and DW_AT_specification in the same unit
+fn origin_attr_value(
+    unit: &Unit<DwarfReader, DwarfReaderOffset>,
+    entry: &DebuggingInformationEntry<DwarfReader>,
+    name: gimli::DwAt,
+) -> Result<Option<AttributeValue<DwarfReader>>> {
+    let mut visited = vec![entry.offset()];
+    origin_attr_value_rec(unit, entry, name, &mut visited)
+}
+
+fn origin_attr_value_rec(
+    unit: &Unit<DwarfReader, DwarfReaderOffset>,
+    entry: &DebuggingInformationEntry<DwarfReader>,
+    name: gimli::DwAt,
+    visited: &mut Vec<UnitOffset<DwarfReaderOffset>>,
+) -> Result<Option<AttributeValue<DwarfReader>>> {
+    if let Some(attr) = entry.attr_value(name)? {
+        return Ok(Some(attr));
+    }
 
-        let frame_base = if let Some(attr) = frame_base_attr {
-            Some(read_wasm_location(attr)?)
-        } else {
-            None
+    for origin in &[gimli::DW_AT_abstract_origin, gimli::DW_AT_specification] {
+        // references into other units are not resolved
+        let offset = match entry.attr_value(*origin)? {
+            Some(AttributeValue::UnitRef(offset)) => offset,
+            _ => continue,
         };
+        if visited.contains(&offset) {
+            continue;
+        }
+        visited.push(offset);
 
-        Subroutine {
-            pc: low_pc..high_pc,
-            name,
-            encoding: unit.encoding(),
-            unit_offset,
-            entry_offset: node.entry().offset(),
-            frame_base,
+        let origin_entry = match unit.entry(offset) {
+            Ok(x) => x,
+            Err(_) => continue,
+        };
+        if let Some(attr) = origin_attr_value_rec(unit, &origin_entry, name, visited)? {
+            return Ok(Some(attr));
         }
-    } else {
-        return Ok(None);
-    };
-    Ok(Some(subroutine))
+    }
+
+    Ok(None)
+}
+
+/// Whether the expression is a DW_OP_WASM_location, which gimli can't evaluate
+pub(crate) fn is_wasm_location<R: gimli::Reader>(expr: &gimli::Expression<R>) -> bool {
+    expr.0.clone().read_u8().ok() == Some(DwAtWasm::DW_OP_WASM_location as u8)
 }
 
-fn read_wasm_location<R: gimli::Reader>(attr_value: AttributeValue<R>) -> Result<WasmLoc> {
+pub(crate) fn read_wasm_location<R: gimli::Reader>(
+    attr_value: AttributeValue<R>,
+) -> Result<WasmLoc> {
     let mut bytes_reader = match attr_value {
         AttributeValue::Exprloc(ref expr) => expr.0.clone(),
         _ => Err(anyhow!("unexpected attribute kind: {:?}", attr_value))?,
@@ -146,21 +300,96 @@ fn read_wasm_location<R: gimli::Reader>(attr_value: AttributeValue<R>) -> Result
     Ok(loc)
 }
 
+/// A pc range of a subroutine, for binary search over the ranges sorted by start
+pub struct SubroutineRange {
+    pc: Range<u64>,
+    /// Greatest end of this and all the preceding ranges, bounds the scan for
+    /// enclosing ranges
+    max_end: u64,
+    /// Index into DwarfSubroutineMap::subroutines
+    index: usize,
+}
+
+pub fn index_subroutine_ranges(subroutines: &[Subroutine]) -> Vec<SubroutineRange> {
+    let mut ranges: Vec<SubroutineRange> = subroutines
+        .iter()
+        .enumerate()
+        .flat_map(|(index, s)| {
+            s.pc.iter().map(move |pc| SubroutineRange {
+                pc: pc.clone(),
+                max_end: pc.end,
+                index,
+            })
+        })
+        .collect();
+    ranges.sort_by_key(|r| r.pc.start);
+
+    let mut max_end = 0;
+    for range in &mut ranges {
+        max_end = std::cmp::max(max_end, range.pc.end);
+        range.max_end = max_end;
+    }
+    ranges
+}
+
 pub struct DwarfSubroutineMap {
     pub subroutines: Vec<Subroutine>,
+    /// Ranges of subroutines, built by index_subroutine_ranges
+    pub ranges: Vec<SubroutineRange>,
     pub dwarf_data: DwarfDebugData,
+    pub lazy_groups: RefCell<LazyGroups>,
+    /// For subprograms without DW_AT_frame_base, the local holding the frame pointer is
+    /// argument count + rbp_local_offset, which depends on the shadow stack convention
+    pub rbp_local_offset: usize,
+    /// Offset of the data segment, for static locals located by DW_OP_addr
+    pub data_base: usize,
 }
 
+/// The toolchains without DW_AT_frame_base keep the frame pointer two locals past the arguments
+pub const DEFAULT_RBP_LOCAL_OFFSET: usize = 2;
+
+/// The shadow stack pointer (__stack_pointer) is the first global of LLVM's output
+const SHADOW_STACK_POINTER_GLOBAL: usize = 0;
+
 impl DwarfSubroutineMap {
-    pub fn find_subroutine(&self, code_offset: usize) -> Result<&Subroutine> {
-        let offset = code_offset as u64;
+    /// Whether any subroutine covers the offset, without parsing units
+    pub fn contains(&self, code_offset: usize) -> bool {
+        self.covering_ranges(code_offset as u64).next().is_some()
+    }
+
+    /// Ranges containing offset, found by binary search
+    fn covering_ranges(&self, offset: u64) -> impl Iterator<Item = &SubroutineRange> {
+        let end = self.ranges.partition_point(|r| r.pc.start <= offset);
+        self.ranges[..end]
+            .iter()
+            .rev()
+            .take_while(move |r| r.max_end > offset)
+            .filter(move |r| r.pc.contains(&offset))
+    }
 
-        match self.subroutines.iter().find(|s| s.pc.contains(&offset)) {
+    /// The innermost subroutine covering the offset
+    pub fn find_subroutine(&self, code_offset: usize) -> Result<&Subroutine> {
+        match self.find_subroutines(code_offset).into_iter().next() {
             Some(s) => Ok(s),
             None => Err(anyhow!("failed to determine subroutine")),
         }
     }
 
+    /// All subroutines covering the offset, the smallest range first
+    pub fn find_subroutines(&self, code_offset: usize) -> Vec<&Subroutine> {
+        let mut subroutines: Vec<(u64, usize)> = self
+            .covering_ranges(code_offset as u64)
+            .map(|r| (r.pc.end - r.pc.start, r.index))
+            .collect();
+
+        // equal ranges keep the order of the debug info
+        subroutines.sort_unstable();
+        subroutines
+            .into_iter()
+            .map(|(_, index)| &self.subroutines[index])
+            .collect()
+    }
+
     pub fn variable_name_list(
         &self,
         code_offset: usize,
@@ -178,48 +407,108 @@ impl DwarfSubroutineMap {
 
         let entry_offset = subroutine.entry_offset;
         let mut variables =
-            variables_in_unit_entry(&dwarf, &unit, Some(entry_offset), offset, group_id)?;
-
-        Ok(variables
-            .iter_mut()
-            .map(|var| {
-                let mut v = VariableName {
-                    name: "<<not parsed yet>>".to_string(),
-                    display_name: "<<not parsed yet>>".to_string(),
-                    type_name: "<<not parsed yet>>".to_string(),
-                    group_id: var.group_id,
-                    child_group_id: var.child_group_id,
-                };
-                if let Some(ref mut name) = var.name {
-                    v.name = std::mem::take(name);
-                }
-                if let Some(ref mut display_name) = var.display_name {
-                    v.display_name = std::mem::take(display_name);
-                }
-                match &var.ty_offset {
-                    TypeDescripter::TypeOffset(offset) => {
-                        if let Ok(ty_name) = unit_type_name(&dwarf, &unit, Some(*offset)) {
-                            v.type_name = ty_name;
-                        }
-                    }
-                    TypeDescripter::Description(desc) => {
-                        v.type_name = desc.clone();
-                    }
-                }
-                v
-            })
-            .collect())
+            variables_in_unit_entry(&dwarf, &unit, Some(entry_offset), offset, group_id, true)?;
+        self.lazy_groups
+            .borrow_mut()
+            .reset(&mut variables, group_id);
+
+        Ok(variable_names(&dwarf, &unit, &mut variables))
+    }
+
+    /// Number of top-level variables listed by variable_name_list, without resolving
+    /// their type names
+    pub fn variable_count(&self, code_offset: usize) -> Result<usize> {
+        let offset = code_offset as u64;
+        let subroutine = self.find_subroutine(code_offset)?;
+
+        let (dwarf, unit) = match self.dwarf_data.unit_offset(subroutine.unit_offset)? {
+            Some(x) => x,
+            None => {
+                return Ok(0);
+            }
+        };
+
+        let entry_offset = subroutine.entry_offset;
+        let variables =
+            variables_in_unit_entry(&dwarf, &unit, Some(entry_offset), offset, 1000, true)?;
+
+        Ok(variables.iter().filter(|v| v.group_id == 1000).count())
+    }
+
+    /// Members of a struct listed by variable_name_list, None for unknown groups
+    pub fn expand_group(
+        &self,
+        code_offset: usize,
+        group_id: i32,
+    ) -> Result<Option<Vec<VariableName>>> {
+        let subroutine = self.find_subroutine(code_offset)?;
+
+        let (dwarf, unit) = match self.dwarf_data.unit_offset(subroutine.unit_offset)? {
+            Some(x) => x,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let variables = self
+            .lazy_groups
+            .borrow_mut()
+            .expand(&dwarf, &unit, group_id)?;
+        Ok(variables.map(|mut variables| variable_names(&dwarf, &unit, &mut variables)))
+    }
+
+    /// Inlined subroutines active at code_offset, innermost first
+    pub fn inlined_subroutines(&self, code_offset: usize) -> Result<Vec<InlinedSubroutine>> {
+        let offset = code_offset as u64;
+        let subroutine = self.find_subroutine(code_offset)?;
+
+        let (dwarf, unit) = match self.dwarf_data.unit_offset(subroutine.unit_offset)? {
+            Some(x) => x,
+            None => {
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut tree = unit.entries_tree(Some(subroutine.entry_offset))?;
+        let root = tree.root()?;
+        let mut inlined = Vec::new();
+        inlined_subroutines_rec(root, &dwarf, &unit, offset, &mut inlined)?;
+        inlined.reverse();
+
+        Ok(inlined)
     }
 
     fn get_frame_base(&self, code_offset: usize) -> Result<Option<WasmLoc>> {
         let subroutine = self.find_subroutine(code_offset)?;
         Ok(subroutine.frame_base.clone())
     }
+
+    /// Number of DW_TAG_formal_parameter of the subprogram at code_offset
+    fn argument_count(&self, code_offset: usize) -> Result<usize> {
+        let subroutine = self.find_subroutine(code_offset)?;
+        let (_, unit) = match self.dwarf_data.unit_offset(subroutine.unit_offset)? {
+            Some(x) => x,
+            None => return Err(anyhow!("unit of the subroutine not found")),
+        };
+        let mut tree = unit.entries_tree(Some(subroutine.entry_offset))?;
+        let root = tree.root()?;
+        let mut children = root.children();
+        let mut count = 0;
+
+        while let Some(child) = children.next()? {
+            if child.entry().tag() == gimli::DW_TAG_formal_parameter {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
     fn display_variable(
         &self,
         code_offset: usize,
         frame_base: FrameBase,
-        name: &String,
+        values: WasmFrameValues,
+        name: &str,
+        printers: &PrettyPrinters,
     ) -> Result<Option<VariableInfo>> {
         let offset = code_offset as u64;
         let subroutine = self.find_subroutine(code_offset)?;
@@ -230,18 +519,22 @@ impl DwarfSubroutineMap {
             }
         };
         let entry_offset = subroutine.entry_offset;
-        let variables = variables_in_unit_entry(&dwarf, &unit, Some(entry_offset), offset, 0)?;
+        let variables =
+            variables_in_unit_entry(&dwarf, &unit, Some(entry_offset), offset, 0, false)?;
 
-        evaluate_variable_from_string(name, &variables, &dwarf, &unit, frame_base)
+        evaluate_variable_from_string(
+            name, &variables, &dwarf, &unit, frame_base, values, offset, printers,
+        )
     }
 
     pub fn get_variable_info(
         &self,
-        opts: &String,
+        opts: &str,
         locals: &WasmValueVector,
         globals: &WasmValueVector,
         stacks: &WasmValueVector,
         code_offset: usize,
+        printers: &PrettyPrinters,
     ) -> Result<Option<VariableInfo>> {
         let frame_base = match self.get_frame_base(code_offset)? {
             Some(loc) => {
@@ -259,31 +552,208 @@ impl DwarfSubroutineMap {
                         .get(idx as usize)
                         .ok_or(anyhow!("failed to get base stack"))?,
                 };
+                // wasm32 addresses are unsigned, don't sign-extend them
                 let offset = match offset.value {
-                    Value::I32(v) => v as u64,
+                    Value::I32(v) => v as u32 as u64,
                     Value::I64(v) => v as u64,
+                    Value::V128(_) => Err(anyhow!("v128 value can't be used as frame base"))?,
                     _ => Err(anyhow!("unexpected frame base value: {:?}", offset.value))?,
                 };
-                FrameBase::WasmFrameBase(offset)
+                FrameBase::WasmFrameBase {
+                    frame_base: offset,
+                    data_base: self.data_base as u64,
+                }
             }
             None => {
-                return Err(anyhow!("failed to get base stack"));
-                // let argument_count = debugger
-                //     .current_frame()
-                //     .ok_or(anyhow!("function frame not found"))?
-                //     .argument_count;
-                // let offset = locals
-                //     .get(argument_count + 2)
-                //     .ok_or(anyhow!("failed to get rbp"))?
-                //     .clone();
-                // let offset = match offset {
-                //     WasmValue::I32(v) => v as u64,
-                //     _ => Err(anyhow!("unexpected frame base value: {:?}", offset))?,
-                // };
-                // FrameBase::RBP(offset)
+                // functions which don't keep a frame pointer address their locals
+                // from the shadow stack pointer
+                let argument_count = self.argument_count(code_offset)?;
+                let offset = locals
+                    .data
+                    .get(argument_count + self.rbp_local_offset)
+                    .or_else(|| globals.data.get(SHADOW_STACK_POINTER_GLOBAL))
+                    .ok_or(anyhow!("failed to get rbp"))?;
+                // wasm32 addresses are unsigned, don't sign-extend them
+                let offset = match offset.value {
+                    Value::I32(v) => v as u32 as u64,
+                    Value::I64(v) => v as u64,
+                    _ => Err(anyhow!("unexpected frame base value: {:?}", offset.value))?,
+                };
+                FrameBase::RBP {
+                    rbp: offset,
+                    data_base: self.data_base as u64,
+                }
             }
         };
 
-        self.display_variable(code_offset, frame_base, opts)
+        let values = WasmFrameValues {
+            locals,
+            globals,
+            stacks,
+        };
+        self.display_variable(code_offset, frame_base, values, opts, printers)
+    }
+
+    /// Evaluate a local with a frame base known to the caller instead of the live locals
+    pub fn get_variable_info_with_frame_base(
+        &self,
+        opts: &str,
+        frame_base: u64,
+        code_offset: usize,
+        printers: &PrettyPrinters,
+    ) -> Result<Option<VariableInfo>> {
+        let frame_base = FrameBase::WasmFrameBase {
+            frame_base,
+            data_base: self.data_base as u64,
+        };
+        let empty = WasmValueVector::new();
+        let values = WasmFrameValues {
+            locals: &empty,
+            globals: &empty,
+            stacks: &empty,
+        };
+        self.display_variable(code_offset, frame_base, values, opts, printers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dwarf::test_utils::{
+        add_base_type, add_composite, add_member, add_typed, debug_data, encoding, first_unit,
+        load_dwarf, write_sections,
+    };
+    use crate::dwarf::transform_dwarf_data;
+    use crate::dwarf::wasm_bindings::WasmValue;
+    use gimli::write::{self, DwarfUnit};
+    use gimli::RunTimeEndian;
+
+    #[test]
+    fn entry_ranges_accept_high_pc_in_any_constant_form() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let root = dwarf_unit.unit.root();
+        for (low_pc, high_pc) in [
+            (0x10, write::AttributeValue::Data4(0x8)),
+            (0x20, write::AttributeValue::Udata(0x4)),
+            (
+                0x30,
+                write::AttributeValue::Address(write::Address::Constant(0x3c)),
+            ),
+        ] {
+            let id = dwarf_unit.unit.add(root, gimli::DW_TAG_inlined_subroutine);
+            let entry = dwarf_unit.unit.get_mut(id);
+            entry.set(
+                gimli::DW_AT_low_pc,
+                write::AttributeValue::Address(write::Address::Constant(low_pc)),
+            );
+            entry.set(gimli::DW_AT_high_pc, high_pc);
+        }
+
+        let sections = write_sections(&mut dwarf_unit, RunTimeEndian::Little);
+        let dwarf = load_dwarf(&sections, RunTimeEndian::Little);
+        let unit = first_unit(&dwarf);
+        let mut ranges = Vec::new();
+        let mut entries = unit.entries();
+        entries.next_dfs().unwrap();
+        while let Some((_, entry)) = entries.next_dfs().unwrap() {
+            ranges.extend(entry_ranges(&dwarf, &unit, entry).unwrap());
+        }
+
+        assert_eq!(ranges, vec![0x10..0x18, 0x20..0x24, 0x30..0x3c]);
+    }
+
+    #[test]
+    fn pointers_of_memory64_are_followed_above_4gib() {
+        // wasm64, the frame base is an i64 local
+        let encoding = gimli::Encoding {
+            address_size: 8,
+            ..encoding(5)
+        };
+        let mut dwarf_unit = DwarfUnit::new(encoding);
+        let unit = &mut dwarf_unit.unit;
+        // every unit is expected to have a line table
+        unit.line_program = write::LineProgram::new(
+            encoding,
+            gimli::LineEncoding::default(),
+            write::LineString::String(b"/src".to_vec()),
+            write::LineString::String(b"main.c".to_vec()),
+            None,
+        );
+        let directory = unit.line_program.default_directory();
+        let file = unit.line_program.add_file(
+            write::LineString::String(b"main.c".to_vec()),
+            directory,
+            None,
+        );
+        unit.line_program
+            .begin_sequence(Some(write::Address::Constant(0x10)));
+        unit.line_program.row().file = file;
+        unit.line_program.generate_row();
+        unit.line_program.end_sequence(0x10);
+
+        let root = unit.root();
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let point = add_composite(unit, gimli::DW_TAG_structure_type, "Point", 8);
+        add_member(unit, point, "x", int, None);
+        add_member(unit, point, "y", int, Some(write::AttributeValue::Udata(4)));
+        let pointer = add_typed(unit, root, gimli::DW_TAG_pointer_type, point);
+        unit.get_mut(pointer)
+            .set(gimli::DW_AT_byte_size, write::AttributeValue::Udata(8));
+
+        let function = unit.add(root, gimli::DW_TAG_subprogram);
+        let entry = unit.get_mut(function);
+        entry.set(
+            gimli::DW_AT_low_pc,
+            write::AttributeValue::Address(write::Address::Constant(0x10)),
+        );
+        entry.set(gimli::DW_AT_high_pc, write::AttributeValue::Udata(0x10));
+        entry.set(
+            gimli::DW_AT_frame_base,
+            write::AttributeValue::Exprloc(write::Expression::raw(vec![
+                DwAtWasm::DW_OP_WASM_location as u8,
+                0,
+                0,
+            ])),
+        );
+        let p = add_typed(unit, function, gimli::DW_TAG_variable, pointer);
+        let entry = unit.get_mut(p);
+        entry.set(
+            gimli::DW_AT_name,
+            write::AttributeValue::String(b"p".to_vec()),
+        );
+        entry.set(
+            gimli::DW_AT_location,
+            write::AttributeValue::Exprloc(write::Expression::raw(vec![gimli::DW_OP_fbreg.0, 8])),
+        );
+
+        let sections = write_sections(&mut dwarf_unit, RunTimeEndian::Little);
+        let debug_info = transform_dwarf_data(debug_data(&sections)).unwrap();
+        let mut locals = WasmValueVector::new();
+        locals.push(WasmValue::from_i64(0x1_0000_1000));
+        let mut info = debug_info
+            .subroutine
+            .get_variable_info(
+                "p->y",
+                &locals,
+                &WasmValueVector::new(),
+                &WasmValueVector::new(),
+                0x18,
+                &PrettyPrinters::default(),
+            )
+            .unwrap()
+            .unwrap();
+
+        // the 8 byte pointer in the frame, then y of the Point it points to
+        let reads: [(u64, usize, u64); 2] =
+            [(0x1_0000_1008, 8, 0x2_0000_0000), (0x2_0000_0004, 4, 42)];
+        let mut result = info.evaluate();
+        for (address, byte_size, value) in reads {
+            assert!(info.is_required_memory_slice());
+            let mut slice = info.required_memory_slice();
+            assert_eq!((slice.address, slice.byte_size), (address, byte_size));
+            slice.set_memory_slice(&value.to_le_bytes()[..byte_size]);
+            result = info.resume_with_memory_slice(slice);
+        }
+        assert_eq!(result, Some("(int)42".to_string()));
     }
 }
@@ -0,0 +1,261 @@
+use gimli::write::{self, DwarfUnit, EndianVec, LineProgram, LineString, Sections};
+use gimli::{Encoding, EndianRcSlice, Format, LineEncoding, RunTimeEndian};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::{Dwarf, DwarfDebugData, DwarfUnit as ReadUnit};
+
+/// 32-bit DWARF of the given version, with the 4 byte addresses of wasm32
+pub(crate) fn encoding(version: u16) -> Encoding {
+    Encoding {
+        format: Format::Dwarf32,
+        version,
+        address_size: 4,
+    }
+}
+
+/// Serialize the unit into sections keyed by their name
+pub(crate) fn write_sections(
+    unit: &mut DwarfUnit,
+    byte_order: RunTimeEndian,
+) -> HashMap<String, Vec<u8>> {
+    let mut sections = Sections::new(EndianVec::new(byte_order));
+    unit.write(&mut sections).unwrap();
+
+    let mut result = HashMap::new();
+    sections
+        .for_each(|id, data| -> gimli::write::Result<()> {
+            if !data.slice().is_empty() {
+                result.insert(id.name().to_string(), data.slice().to_vec());
+            }
+            Ok(())
+        })
+        .unwrap();
+    result
+}
+
+/// Load the sections the same way DwarfDebugData does, in the given byte order
+pub(crate) fn load_dwarf(sections: &HashMap<String, Vec<u8>>, byte_order: RunTimeEndian) -> Dwarf {
+    let load_section = |id: gimli::SectionId| -> gimli::Result<_> {
+        let data: Rc<[u8]> = match sections.get(id.name()) {
+            Some(data) => Rc::from(data.as_slice()),
+            None => Rc::from(&[][..]),
+        };
+        Ok(EndianRcSlice::new(data, byte_order))
+    };
+    Dwarf::load(&load_section).unwrap()
+}
+
+pub(crate) fn first_unit(dwarf: &Dwarf) -> ReadUnit {
+    let header = dwarf.units().next().unwrap().unwrap();
+    dwarf.unit(header).unwrap()
+}
+
+/// DwarfDebugData holding the sections as if they were custom sections of a module
+pub(crate) fn debug_data(sections: &HashMap<String, Vec<u8>>) -> DwarfDebugData {
+    DwarfDebugData {
+        program_raw_data: sections
+            .iter()
+            .map(|(name, data)| (name.clone(), Rc::from(data.as_slice())))
+            .collect(),
+        supplementary_raw_data: HashMap::new(),
+        cache: Rc::new(RefCell::new(Default::default())),
+    }
+}
+
+/// A line sequence: start address, (offset, line, column) of its rows and the end offset
+pub(crate) type Sequence<'a> = (u64, &'a [(u64, u64, u64)], u64);
+
+/// Sections of a unit of /src/main.c whose rows in the given sequences refer to file,
+/// a file added in /src
+pub(crate) fn line_table(
+    version: u16,
+    file: &str,
+    sequences: &[Sequence],
+) -> HashMap<String, Vec<u8>> {
+    let encoding = encoding(version);
+    let mut dwarf_unit = DwarfUnit::new(encoding);
+    let mut program = LineProgram::new(
+        encoding,
+        LineEncoding::default(),
+        LineString::String(b"/src".to_vec()),
+        LineString::String(b"main.c".to_vec()),
+        None,
+    );
+    let directory = program.default_directory();
+    let file = program.add_file(LineString::String(file.into()), directory, None);
+
+    for (start, rows, end) in sequences {
+        program.begin_sequence(Some(write::Address::Constant(*start)));
+        for (offset, line, column) in rows.iter() {
+            let row = program.row();
+            row.address_offset = *offset;
+            row.file = file;
+            row.line = *line;
+            row.column = *column;
+            program.generate_row();
+        }
+        program.end_sequence(*end);
+    }
+    dwarf_unit.unit.line_program = program;
+    let root = dwarf_unit.unit.root();
+    for (name, value) in [
+        (gimli::DW_AT_name, "main.c"),
+        (gimli::DW_AT_comp_dir, "/src"),
+    ] {
+        dwarf_unit
+            .unit
+            .get_mut(root)
+            .set(name, write::AttributeValue::String(value.into()));
+    }
+
+    write_sections(&mut dwarf_unit, RunTimeEndian::Little)
+}
+
+/// Module with the sections as custom sections, followed by an empty code section
+pub(crate) fn wasm_module(sections: &HashMap<String, Vec<u8>>) -> Vec<u8> {
+    let mut module = b"\0asm\x01\0\0\0".to_vec();
+    let mut names: Vec<&String> = sections.keys().collect();
+    names.sort();
+
+    for name in names {
+        let mut payload = Vec::new();
+        write_uleb128(&mut payload, name.len() as u64);
+        payload.extend_from_slice(name.as_bytes());
+        payload.extend_from_slice(&sections[name]);
+
+        module.push(0);
+        write_uleb128(&mut module, payload.len() as u64);
+        module.append(&mut payload);
+    }
+    // code section of no functions
+    module.extend_from_slice(&[10, 1, 0]);
+    module
+}
+
+/// Append a data section of one empty active segment placed at offset
+pub(crate) fn push_data_segment(module: &mut Vec<u8>, offset: u8) {
+    assert!(offset < 0x40, "offset must fit in a one byte sleb128");
+    // segment count, memory 0, i32.const offset, end, no bytes
+    module.extend_from_slice(&[11, 6, 1, 0, 0x41, offset, 0x0b, 0]);
+}
+
+pub(crate) fn write_uleb128(buffer: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buffer.push(byte);
+            return;
+        }
+        buffer.push(byte | 0x80);
+    }
+}
+
+pub(crate) fn add_named(
+    unit: &mut write::Unit,
+    parent: write::UnitEntryId,
+    tag: gimli::DwTag,
+    name: &str,
+) -> write::UnitEntryId {
+    let id = unit.add(parent, tag);
+    unit.get_mut(id).set(
+        gimli::DW_AT_name,
+        write::AttributeValue::String(name.as_bytes().to_vec()),
+    );
+    id
+}
+
+/// Add an entry whose DW_AT_type refers to ty
+pub(crate) fn add_typed(
+    unit: &mut write::Unit,
+    parent: write::UnitEntryId,
+    tag: gimli::DwTag,
+    ty: write::UnitEntryId,
+) -> write::UnitEntryId {
+    let id = unit.add(parent, tag);
+    unit.get_mut(id)
+        .set(gimli::DW_AT_type, write::AttributeValue::UnitRef(ty));
+    id
+}
+
+/// Add a structure, class or union of the given size to the root
+pub(crate) fn add_composite(
+    unit: &mut write::Unit,
+    tag: gimli::DwTag,
+    name: &str,
+    byte_size: u64,
+) -> write::UnitEntryId {
+    let root = unit.root();
+    let id = add_named(unit, root, tag, name);
+    unit.get_mut(id).set(
+        gimli::DW_AT_byte_size,
+        write::AttributeValue::Udata(byte_size),
+    );
+    id
+}
+
+pub(crate) fn add_base_type(
+    unit: &mut write::Unit,
+    name: &str,
+    encoding: gimli::DwAte,
+    byte_size: u64,
+) -> write::UnitEntryId {
+    let id = add_composite(unit, gimli::DW_TAG_base_type, name, byte_size);
+    unit.get_mut(id).set(
+        gimli::DW_AT_encoding,
+        write::AttributeValue::Encoding(encoding),
+    );
+    id
+}
+
+/// Add a member of type ty, without DW_AT_data_member_location when location is None
+pub(crate) fn add_member(
+    unit: &mut write::Unit,
+    parent: write::UnitEntryId,
+    name: &str,
+    ty: write::UnitEntryId,
+    location: Option<write::AttributeValue>,
+) -> write::UnitEntryId {
+    let id = add_named(unit, parent, gimli::DW_TAG_member, name);
+    let entry = unit.get_mut(id);
+    entry.set(gimli::DW_AT_type, write::AttributeValue::UnitRef(ty));
+    if let Some(location) = location {
+        entry.set(gimli::DW_AT_data_member_location, location);
+    }
+    id
+}
+
+/// Add a global variable of type ty, at the static address if there is one
+pub(crate) fn add_variable(
+    unit: &mut write::Unit,
+    name: &str,
+    ty: write::UnitEntryId,
+    address: Option<u32>,
+) -> write::UnitEntryId {
+    let root = unit.root();
+    let id = add_named(unit, root, gimli::DW_TAG_variable, name);
+    let entry = unit.get_mut(id);
+    entry.set(gimli::DW_AT_type, write::AttributeValue::UnitRef(ty));
+    if let Some(address) = address {
+        let mut location = vec![gimli::DW_OP_addr.0];
+        location.extend_from_slice(&address.to_le_bytes());
+        entry.set(
+            gimli::DW_AT_location,
+            write::AttributeValue::Exprloc(write::Expression::raw(location)),
+        );
+    }
+    id
+}
+
+/// Offset of the first entry with the tag, in depth-first order
+pub(crate) fn find_entry(unit: &ReadUnit, tag: gimli::DwTag) -> Option<gimli::UnitOffset> {
+    let mut entries = unit.entries();
+    while let Some((_, entry)) = entries.next_dfs().unwrap() {
+        if entry.tag() == tag {
+            return Some(entry.offset());
+        }
+    }
+    None
+}
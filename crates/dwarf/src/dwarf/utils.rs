@@ -1,8 +1,9 @@
-use anyhow::Result;
-use gimli;
-use regex::{Captures, Regex};
+use anyhow::{anyhow, Result};
+use gimli::{self, Endianity, Section};
+#[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+#[cfg(target_arch = "wasm32")]
 #[wasm_bindgen]
 extern "C" {
     // Use `js_namespace` here to bind `console.log(..)` instead of just
@@ -11,6 +12,12 @@ extern "C" {
     pub fn error(s: &str);
 }
 
+/// console is only reachable from wasm, native builds such as the unit tests print to stderr
+#[cfg(not(target_arch = "wasm32"))]
+pub fn error(s: &str) {
+    eprintln!("{}", s);
+}
+
 #[macro_export]
 macro_rules! console_log {
     // Note that this is using the `log` function imported above during
@@ -23,44 +30,116 @@ pub(crate) fn clone_string_attribute<R: gimli::Reader>(
     unit: &gimli::Unit<R, R::Offset>,
     attr: gimli::AttributeValue<R>,
 ) -> Result<String> {
-    Ok(dwarf
-        .attr_string(unit, attr)?
-        .to_string()?
-        .as_ref()
-        .to_string())
+    let string = match dwarf.attr_string(unit, attr.clone()) {
+        Ok(x) => x,
+        // DWARF 5 string forms are resolved through .debug_str_offsets / .debug_line_str
+        Err(e) => return Err(anyhow!("failed to read string {:?}: {}", attr, e)),
+    };
+    Ok(string.to_string()?.as_ref().to_string())
+}
+
+/// Byte order of the data described by the debug info
+pub(crate) fn reader_byte_order<R: gimli::Reader>(dwarf: &gimli::Dwarf<R>) -> gimli::RunTimeEndian {
+    if dwarf.debug_info.reader().endian().is_big_endian() {
+        gimli::RunTimeEndian::Big
+    } else {
+        gimli::RunTimeEndian::Little
+    }
 }
 
-pub(crate) fn convert_from_windows_stype_path(path: &String) -> String {
+/// Demangle Rust or C++ symbol names, or return them unchanged
+pub(crate) fn demangle_name(name: &str) -> String {
+    if let Ok(demangled) = rustc_demangle::try_demangle(name) {
+        return format!("{:#}", demangled);
+    }
+
+    match cpp_demangle::Symbol::new(name) {
+        Ok(symbol) => symbol.to_string(),
+        Err(_) => name.to_string(),
+    }
+}
+
+pub(crate) fn convert_from_windows_stype_path(path: &str) -> String {
     let backslash_escaped = path.replace('\\', "/");
-    let regex = Regex::new("^([A-Za-z]):/");
-    regex
-        .unwrap()
-        .replace_all(&backslash_escaped, |captured: &Captures| {
-            format!("{}:/", captured[1].to_lowercase())
-        })
-        .into_owned()
+    if is_windows_path(&backslash_escaped) {
+        backslash_escaped[..1].to_lowercase() + &backslash_escaped[1..]
+    } else {
+        backslash_escaped
+    }
+}
+
+/// Paths starting with a drive letter, which compare case-insensitively
+pub(crate) fn is_windows_path(path: &str) -> bool {
+    match path.as_bytes() {
+        [drive, b':', b'/', ..] => drive.is_ascii_alphabetic(),
+        _ => false,
+    }
 }
 
 pub(crate) fn is_absolute_path(path: &str) -> bool {
-    let regex = Regex::new("^([A-Za-z]):/").unwrap();
-    path.starts_with('/') | regex.is_match(path)
+    path.starts_with('/') | is_windows_path(path)
 }
 
-pub(crate) fn normalize_path(path: &String) -> String {
-    let splited = path.split('/');
+/// Resolve "." and ".." components, keeping the root of absolute paths and the
+/// leading ".." of relative ones
+pub(crate) fn normalize_path(path: &str) -> String {
+    let (root, rest) = if let Some(rest) = path.strip_prefix('/') {
+        ("/", rest)
+    } else if is_windows_path(path) {
+        path.split_at(3)
+    } else {
+        ("", path)
+    };
     let mut stack = Vec::new();
 
-    for component in splited {
+    for component in rest.split('/') {
         match component {
-            ".." => {
-                stack.pop();
-            }
-            "." => {
+            ".." => match stack.last() {
+                Some(&"..") | None => {
+                    // nothing is above the root
+                    if root.is_empty() {
+                        stack.push(component);
+                    }
+                }
+                Some(_) => {
+                    stack.pop();
+                }
+            },
+            "." | "" => {
                 // nothing to do
             }
             other => stack.push(other),
         }
     }
 
-    stack.join("/")
+    format!("{}{}", root, stack.join("/"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_path_keeps_roots_and_leading_parents() {
+        assert_eq!(normalize_path("/a/../b"), "/b");
+        assert_eq!(normalize_path("/a/./b//c/"), "/a/b/c");
+        // nothing is above the root
+        assert_eq!(normalize_path("/../a"), "/a");
+        assert_eq!(normalize_path("c:/x/../../y"), "c:/y");
+        // relative paths keep the parents they can't resolve
+        assert_eq!(normalize_path("a/../../b"), "../b");
+        assert_eq!(normalize_path("../../a/b/.."), "../../a");
+        assert_eq!(normalize_path("a/.."), "");
+    }
+
+    #[test]
+    fn drive_letters_are_lowercased_and_absolute() {
+        let converted = convert_from_windows_stype_path("C:\\src\\Main.c");
+        assert_eq!(converted, "c:/src/Main.c");
+        assert!(is_absolute_path(&converted));
+        assert!(is_absolute_path("/src/main.c"));
+        // a drive letter needs the separator
+        assert!(!is_windows_path("c:main.c"));
+        assert!(!is_absolute_path("src/c:/main.c"));
+    }
 }
@@ -1,14 +1,23 @@
 use anyhow::{anyhow, Result};
 use gimli::{
-    AttributeValue, DebuggingInformationEntry, Expression, Reader, Unit, UnitOffset,
-    UnitSectionOffset,
+    AttributeValue, DebuggingInformationEntry, Endianity, Expression, Reader, ReaderOffset,
+    RunTimeEndian, Unit, UnitOffset, UnitSectionOffset,
 };
+use num_bigint::BigInt;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
 
-use super::utils::{clone_string_attribute, error};
+use super::format::{unsigned_from_bytes, FixedScale};
+use super::pretty_printer::{
+    MemberLayout, PrettyLayout, PrettyPrinterKind, PrettyPrinters, VariantField, VariantName,
+};
+use super::sourcemap::file_index_path;
+use super::subroutine::{entry_ranges, is_wasm_location, read_wasm_location, WasmLoc};
+use super::utils::{clone_string_attribute, error, reader_byte_order};
 use super::wasm_bindings::WasmValueVector;
 use super::{
-    unit_type_name, DwarfDebugData, DwarfReader, DwarfReaderOffset, MemorySlice,
-    VariableEvaluationResult, VariableInfo,
+    unit_type_name, DwarfDebugData, DwarfReader, DwarfReaderOffset, MemorySlice, VariableInfo,
 };
 use crate::console_log;
 
@@ -18,8 +27,18 @@ pub struct VariableName {
     pub type_name: String,
     pub group_id: i32,
     pub child_group_id: Option<i32>,
+    /// Offset of the originating DIE in its unit, stable across steps
+    pub die_offset: usize,
+    pub type_offset: Option<usize>,
+    pub byte_size: usize,
+    pub encoding: Option<gimli::DwAte>,
+    pub is_parameter: bool,
+    pub scope_depth: u32,
+    pub decl_file: Option<String>,
+    pub decl_line: Option<u64>,
 }
 
+#[derive(Clone)]
 pub struct SymbolVariable {
     pub name: Option<String>,
     pub display_name: Option<String>,
@@ -27,11 +46,58 @@ pub struct SymbolVariable {
     pub ty_offset: TypeDescripter,
     pub group_id: i32,
     pub child_group_id: Option<i32>,
+    pub die_offset: usize,
+    /// Class DIE whose members are listed on demand by LazyGroups
+    pub members_offset: Option<usize>,
+    /// Bitfield members: offset from the least significant bit of the storage unit
+    pub bit_offset: Option<u64>,
+    pub bit_size: Option<u64>,
+    /// DW_TAG_formal_parameter, listed in declaration order
+    pub is_parameter: bool,
+    /// Number of lexical blocks enclosing the variable inside its subprogram
+    pub scope_depth: u32,
+    /// DW_AT_decl_file resolved through the line program, and DW_AT_decl_line
+    pub decl_file: Option<String>,
+    pub decl_line: Option<u64>,
+}
+
+impl Default for SymbolVariable {
+    fn default() -> Self {
+        Self {
+            name: None,
+            display_name: None,
+            contents: Vec::new(),
+            ty_offset: TypeDescripter::Description(String::new()),
+            group_id: 0,
+            child_group_id: None,
+            die_offset: 0,
+            members_offset: None,
+            bit_offset: None,
+            bit_size: None,
+            is_parameter: false,
+            scope_depth: 0,
+            decl_file: None,
+            decl_line: None,
+        }
+    }
+}
+
+/// A variable expression whose name matches no variable in scope
+#[derive(Debug)]
+pub struct VariableNotFound(pub String);
+
+impl std::fmt::Display for VariableNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "'{}' is not valid variable name", self.0)
+    }
 }
 
+impl std::error::Error for VariableNotFound {}
+
 #[derive(Clone)]
 pub enum VariableExpression {
     Location(gimli::AttributeValue<DwarfReader>),
+    MemberLocation(gimli::AttributeValue<DwarfReader>),
     ConstValue(Vec<u8>),
     Pointer,
     Unknown { debug_info: String },
@@ -42,6 +108,29 @@ pub enum VariableLocation {
     Address(u64),
     Offset(i64),
     Pointer,
+    /// Offset of (the integer read at address + bias) * scale, for the strides of arrays
+    /// whose bounds are held in memory
+    Scaled {
+        address: u64,
+        byte_size: usize,
+        bias: i64,
+        scale: i64,
+    },
+    /// Address computed from the current one by a DW_AT_data_member_location expression
+    /// which reads memory, e.g. the offset of a virtual base. reads are the loads made
+    /// by the expression so far, in order
+    Member {
+        expression: Expression<DwarfReader>,
+        encoding: gimli::Encoding,
+        reads: Vec<Vec<u8>>,
+    },
+}
+
+/// A part of a variable described by DW_OP_piece
+#[derive(Clone)]
+pub enum VariablePiece {
+    Memory { address: u64, byte_size: usize },
+    Value(Vec<u8>),
 }
 
 #[derive(Clone)]
@@ -50,10 +139,24 @@ pub enum TypeDescripter {
     Description(String),
 }
 
+/// Base addresses for location expressions; locals carry the data base as well
+/// for static variables located by DW_OP_addr
+#[derive(Clone)]
+#[allow(clippy::enum_variant_names, clippy::upper_case_acronyms)]
 pub enum FrameBase {
-    WasmFrameBase(u64),
+    WasmFrameBase { frame_base: u64, data_base: u64 },
     WasmDataBase(u64),
-    RBP(u64),
+    RBP { rbp: u64, data_base: u64 },
+}
+
+impl FrameBase {
+    fn data_base(&self) -> u64 {
+        match self {
+            FrameBase::WasmFrameBase { data_base, .. } => *data_base,
+            FrameBase::WasmDataBase(data_base) => *data_base,
+            FrameBase::RBP { data_base, .. } => *data_base,
+        }
+    }
 }
 
 /**
@@ -65,6 +168,7 @@ pub fn variables_in_unit_entry(
     entry_offset: Option<UnitOffset<DwarfReaderOffset>>,
     code_offset: u64,
     root_group_id: i32,
+    lazy: bool,
 ) -> Result<Vec<SymbolVariable>> {
     let mut tree = unit.entries_tree(entry_offset)?;
     let root = tree.root()?;
@@ -78,10 +182,203 @@ pub fn variables_in_unit_entry(
         &mut variables,
         root_group_id,
         &mut root_group_id,
+        lazy,
     )?;
     Ok(variables)
 }
 
+/// Roots of the listings are below 10000 (1000 for locals, 1001 for globals), the groups
+/// under a root are numbered from here so that those of different roots never meet
+fn first_child_group_id(root_group_id: i32) -> i32 {
+    (root_group_id - 1000 + 1) * 10000
+}
+
+/// Struct variables whose members haven't been listed yet, by child group id
+#[derive(Default)]
+pub struct LazyGroups {
+    variables: HashMap<i32, SymbolVariable>,
+    /// Entries cut from a listing by max_variables, by the child group id of the
+    /// entry standing in for them
+    truncated: HashMap<i32, Vec<SymbolVariable>>,
+    /// Nesting depth of the members of each group, the listing itself is at depth 1
+    depths: HashMap<i32, usize>,
+    next_group_id: i32,
+    /// Entries of a listing or an expanded group, None for no limit
+    pub max_variables: Option<usize>,
+    /// Members nested deeper are listed behind an entry which loads them when expanded,
+    /// None for no limit
+    pub max_depth: Option<usize>,
+}
+
+impl LazyGroups {
+    /// Forget the previous listing and remember the unexpanded groups of variables.
+    /// The listing is truncated to max_variables
+    pub fn reset(&mut self, variables: &mut Vec<SymbolVariable>, root_group_id: i32) {
+        self.variables.clear();
+        self.truncated.clear();
+        self.depths.clear();
+        self.next_group_id = first_child_group_id(root_group_id);
+        self.remember(variables, 1);
+        self.truncate(variables, root_group_id);
+    }
+
+    /// List the direct members of a group, None if the group is unknown
+    pub fn expand(
+        &mut self,
+        dwarf: &gimli::Dwarf<DwarfReader>,
+        unit: &Unit<DwarfReader>,
+        group_id: i32,
+    ) -> Result<Option<Vec<SymbolVariable>>> {
+        if let Some(rest) = self.truncated.get(&group_id) {
+            let mut variables = rest.clone();
+            self.truncate(&mut variables, group_id);
+            return Ok(Some(variables));
+        }
+
+        let parent = match self.variables.get(&group_id) {
+            Some(parent) => parent.clone(),
+            None => return Ok(None),
+        };
+        let depth = self.depths.get(&group_id).copied().unwrap_or(1);
+        if matches!(self.max_depth, Some(max_depth) if depth > max_depth) {
+            return Ok(Some(vec![self.defer(parent, group_id)]));
+        }
+        let mut variables = Vec::new();
+
+        if let Some(offset) = parent.members_offset {
+            let mut tree = unit.entries_tree(Some(UnitOffset(offset)))?;
+            let root = tree.root()?;
+            structure_members(
+                root,
+                dwarf,
+                unit,
+                &parent,
+                &mut variables,
+                &mut self.next_group_id,
+                true,
+                &mut Vec::new(),
+            )?;
+        }
+
+        self.remember(&variables, depth);
+        self.truncate(&mut variables, group_id);
+        Ok(Some(variables))
+    }
+
+    /// Entry of the group standing in for the members of parent, which are listed
+    /// under a new group when it is expanded. The depth counts from there again
+    fn defer(&mut self, mut parent: SymbolVariable, group_id: i32) -> SymbolVariable {
+        let deferred_group_id = self.next_group_id;
+        self.next_group_id += 1;
+
+        parent.child_group_id = Some(deferred_group_id);
+        self.variables.insert(deferred_group_id, parent);
+        self.depths.insert(deferred_group_id, 1);
+
+        SymbolVariable {
+            name: Some("<truncated>".to_string()),
+            display_name: Some("<nested too deep, expand to load>".to_string()),
+            group_id,
+            child_group_id: Some(deferred_group_id),
+            ..Default::default()
+        }
+    }
+
+    /// Keep max_variables entries and move the rest behind an entry of the group,
+    /// which lists them when expanded
+    fn truncate(&mut self, variables: &mut Vec<SymbolVariable>, group_id: i32) {
+        let max_variables = match self.max_variables {
+            Some(max) if variables.len() > max => max,
+            _ => return,
+        };
+
+        let mut rest = variables.split_off(max_variables);
+        let rest_group_id = self.next_group_id;
+        self.next_group_id += 1;
+        for var in &mut rest {
+            if var.group_id == group_id {
+                var.group_id = rest_group_id;
+            }
+        }
+
+        variables.push(SymbolVariable {
+            name: Some("<truncated>".to_string()),
+            display_name: Some(format!("<{} more, expand to load>", rest.len())),
+            group_id,
+            child_group_id: Some(rest_group_id),
+            ..Default::default()
+        });
+        self.truncated.insert(rest_group_id, rest);
+    }
+
+    /// Record the groups of variables listed at depth
+    fn remember(&mut self, variables: &[SymbolVariable], depth: usize) {
+        for var in variables {
+            if let Some(group_id) = var.child_group_id {
+                // namespaces take group ids too, new groups must not collide with them
+                self.next_group_id = std::cmp::max(self.next_group_id, group_id + 1);
+                self.depths.insert(group_id, depth + 1);
+                if var.members_offset.is_some() {
+                    self.variables.insert(group_id, var.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Build the variable list entries shown to the frontend
+pub fn variable_names(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader>,
+    variables: &mut [SymbolVariable],
+) -> Vec<VariableName> {
+    variables
+        .iter_mut()
+        .map(|var| {
+            let mut v = VariableName {
+                name: "<<not parsed yet>>".to_string(),
+                display_name: "<<not parsed yet>>".to_string(),
+                type_name: "<<not parsed yet>>".to_string(),
+                group_id: var.group_id,
+                child_group_id: var.child_group_id,
+                die_offset: var.die_offset,
+                type_offset: None,
+                byte_size: 0,
+                encoding: None,
+                is_parameter: var.is_parameter,
+                scope_depth: var.scope_depth,
+                decl_file: var.decl_file.take(),
+                decl_line: var.decl_line,
+            };
+            if let Some(ref mut name) = var.name {
+                v.name = std::mem::take(name);
+            }
+            if let Some(ref mut display_name) = var.display_name {
+                v.display_name = std::mem::take(display_name);
+            }
+            match &var.ty_offset {
+                TypeDescripter::TypeOffset(offset) => {
+                    v.type_offset = Some(*offset);
+                    if let Ok(ty_name) = unit_type_name(dwarf, unit, Some(*offset)) {
+                        v.type_name = ty_name;
+                    }
+                    if let Ok((byte_size, encoding)) = type_layout(unit, UnitOffset(*offset)) {
+                        v.byte_size = byte_size.unwrap_or(0) as usize;
+                        v.encoding = encoding;
+                    }
+                }
+                TypeDescripter::Description(desc) => {
+                    v.type_name = desc.clone();
+                }
+            }
+
+            v
+        })
+        .collect()
+}
+
+/// When lazy is set, members of structs are not listed until LazyGroups::expand
+#[allow(clippy::too_many_arguments)]
 fn variables_in_unit_entry_recursive(
     node: gimli::EntriesTreeNode<DwarfReader>,
     dwarf: &gimli::Dwarf<DwarfReader>,
@@ -90,11 +387,12 @@ fn variables_in_unit_entry_recursive(
     variables: &mut Vec<SymbolVariable>,
     root_group_id: i32,
     group_id: &mut i32,
+    lazy: bool,
 ) -> Result<()> {
     let mut children = node.children();
 
     if *group_id < 10000 {
-        *group_id = (*group_id - 1000 + 1) * 10000;
+        *group_id = first_child_group_id(*group_id);
     } else {
         *group_id += 1;
     }
@@ -103,33 +401,34 @@ fn variables_in_unit_entry_recursive(
         match child.entry().tag() {
             gimli::DW_TAG_variable | gimli::DW_TAG_formal_parameter => {
                 let mut var = transform_variable(dwarf, unit, child.entry(), root_group_id)?;
-                structure_variable_recursive(child, dwarf, unit, &mut var, variables, group_id)?;
+                structure_variable_recursive(
+                    child,
+                    dwarf,
+                    unit,
+                    &mut var,
+                    variables,
+                    group_id,
+                    lazy,
+                    &mut Vec::new(),
+                )?;
                 variables.push(var);
             }
             gimli::DW_TAG_lexical_block => {
-                let low_pc_attr = child.entry().attr_value(gimli::DW_AT_low_pc)?;
-                let high_pc_attr = child.entry().attr_value(gimli::DW_AT_high_pc)?;
-
-                if let Some(AttributeValue::Addr(low_pc)) = low_pc_attr {
-                    let high_pc = match high_pc_attr {
-                        Some(AttributeValue::Udata(size)) => low_pc + size,
-                        Some(AttributeValue::Addr(high_pc)) => high_pc,
-                        Some(x) => unreachable!("high_pc can't be {:?}", x),
-                        None => continue,
-                    };
-
-                    let code_range = low_pc..high_pc;
-
-                    if code_range.contains(&code_offset) {
-                        variables_in_unit_entry_recursive(
-                            child,
-                            dwarf,
-                            unit,
-                            code_offset,
-                            variables,
-                            root_group_id,
-                            group_id,
-                        )?;
+                let ranges = entry_ranges(dwarf, unit, child.entry())?;
+                if ranges.iter().any(|range| range.contains(&code_offset)) {
+                    let first = variables.len();
+                    variables_in_unit_entry_recursive(
+                        child,
+                        dwarf,
+                        unit,
+                        code_offset,
+                        variables,
+                        root_group_id,
+                        group_id,
+                        lazy,
+                    )?;
+                    for var in &mut variables[first..] {
+                        var.scope_depth += 1;
                     }
                 }
             }
@@ -144,6 +443,7 @@ fn variables_in_unit_entry_recursive(
                     variables,
                     *group_id,
                     group_id,
+                    lazy,
                 )?;
                 variables.push(var);
             }
@@ -153,6 +453,7 @@ fn variables_in_unit_entry_recursive(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn structure_variable_recursive(
     node: gimli::EntriesTreeNode<DwarfReader>,
     dwarf: &gimli::Dwarf<DwarfReader>,
@@ -160,51 +461,36 @@ fn structure_variable_recursive(
     parent_variable: &mut SymbolVariable,
     variables: &mut Vec<SymbolVariable>,
     group_id: &mut i32,
+    lazy: bool,
+    visited: &mut Vec<usize>,
 ) -> Result<()> {
+    // types being expanded on the way here, a struct reached again through
+    // its own members would never end
+    let offset = node.entry().offset().0;
+    if visited.contains(&offset) {
+        console_log!("recursive type found at 0x{:x}", offset);
+        return Ok(());
+    }
+    visited.push(offset);
+
     match node.entry().tag() {
         gimli::DW_TAG_class_type | gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type => {
-            let mut children = node.children();
-            let current_group_id = *group_id;
-            parent_variable.child_group_id = Some(current_group_id);
+            parent_variable.child_group_id = Some(*group_id);
             *group_id += 1;
 
-            while let Some(child) = children.next()? {
-                match child.entry().tag() {
-                    gimli::DW_TAG_member => {
-                        let mut var =
-                            transform_variable(dwarf, unit, child.entry(), current_group_id)?;
-
-                        let mut contents = parent_variable.contents.clone();
-                        contents.append(&mut var.contents);
-
-                        let mut var = SymbolVariable {
-                            display_name: Some(format!(
-                                "{}.{}",
-                                parent_variable
-                                    .name
-                                    .as_ref()
-                                    .unwrap_or(&"<unnamed>".to_string()),
-                                var.name.as_ref().unwrap_or(&"<unnamed>".to_string())
-                            )),
-                            name: Some(var.name.unwrap_or("<unnamed>".to_string())),
-                            contents,
-                            ty_offset: var.ty_offset,
-                            group_id: var.group_id,
-                            child_group_id: var.child_group_id,
-                        };
-
-                        if let TypeDescripter::TypeOffset(offset) = var.ty_offset {
-                            let mut tree = unit.entries_tree(Some(UnitOffset(offset)))?;
-                            let root = tree.root()?;
-                            structure_variable_recursive(
-                                root, dwarf, unit, &mut var, variables, group_id,
-                            )?;
-                        }
-
-                        variables.push(var);
-                    }
-                    _ => continue,
-                }
+            if lazy {
+                parent_variable.members_offset = Some(node.entry().offset().0);
+            } else {
+                structure_members(
+                    node,
+                    dwarf,
+                    unit,
+                    parent_variable,
+                    variables,
+                    group_id,
+                    false,
+                    visited,
+                )?;
             }
         }
         gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => {
@@ -223,11 +509,15 @@ fn structure_variable_recursive(
                         parent_variable,
                         variables,
                         group_id,
+                        lazy,
+                        visited,
                     )?;
                 }
             }
         }
-        gimli::DW_TAG_const_type => {
+        // the value is an offset, the member type isn't laid out here
+        gimli::DW_TAG_ptr_to_member_type => {}
+        gimli::DW_TAG_const_type | gimli::DW_TAG_volatile_type | gimli::DW_TAG_restrict_type => {
             if let Some(AttributeValue::UnitRef(ref offset)) =
                 node.entry().attr_value(gimli::DW_AT_type)?
             {
@@ -241,6 +531,8 @@ fn structure_variable_recursive(
                         parent_variable,
                         variables,
                         group_id,
+                        lazy,
+                        visited,
                     )?;
                 }
             }
@@ -259,12 +551,207 @@ fn structure_variable_recursive(
                         parent_variable,
                         variables,
                         group_id,
+                        lazy,
+                        visited,
+                    )?;
+                }
+            }
+        }
+    }
+
+    visited.pop();
+    Ok(())
+}
+
+/// Flatten the members of a class into variables, including the members
+/// inherited from its base classes
+#[allow(clippy::too_many_arguments)]
+fn structure_members(
+    node: gimli::EntriesTreeNode<DwarfReader>,
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader>,
+    parent_variable: &SymbolVariable,
+    variables: &mut Vec<SymbolVariable>,
+    group_id: &mut i32,
+    lazy: bool,
+    visited: &mut Vec<usize>,
+) -> Result<()> {
+    let current_group_id = parent_variable.child_group_id.unwrap_or_default();
+    let mut children = node.children();
+
+    while let Some(child) = children.next()? {
+        match child.entry().tag() {
+            gimli::DW_TAG_member => {
+                let mut var = transform_variable(dwarf, unit, child.entry(), current_group_id)?;
+
+                // members of a union and often the first member of a struct have no
+                // DW_AT_data_member_location, they are at offset 0 of the parent.
+                // static members are declarations located elsewhere
+                let is_declaration = child
+                    .entry()
+                    .attr_value(gimli::DW_AT_declaration)?
+                    .is_some();
+                if var.contents.is_empty() && !is_declaration {
+                    var.contents
+                        .push(VariableExpression::MemberLocation(AttributeValue::Udata(0)));
+                }
+
+                let mut member_contents = parent_variable.contents.clone();
+                member_contents.append(&mut var.contents);
+
+                let mut var = SymbolVariable {
+                    display_name: Some(format!(
+                        "{}.{}",
+                        parent_variable
+                            .name
+                            .as_ref()
+                            .unwrap_or(&"<unnamed>".to_string()),
+                        var.name.as_ref().unwrap_or(&"<unnamed>".to_string())
+                    )),
+                    name: Some(var.name.unwrap_or("<unnamed>".to_string())),
+                    contents: member_contents,
+                    is_parameter: false,
+                    ..var
+                };
+
+                if let TypeDescripter::TypeOffset(offset) = var.ty_offset {
+                    let mut tree = unit.entries_tree(Some(UnitOffset(offset)))?;
+                    let root = tree.root()?;
+                    structure_variable_recursive(
+                        root, dwarf, unit, &mut var, variables, group_id, lazy, visited,
+                    )?;
+                }
+
+                variables.push(var);
+            }
+            gimli::DW_TAG_inheritance => {
+                let entry = child.entry();
+
+                // members of the base are flattened into the derived class, the location
+                // of a virtual base is read from the object when it is evaluated
+                let mut base = parent_variable.clone();
+                if let Some(location) = entry.attr_value(gimli::DW_AT_data_member_location)? {
+                    base.contents
+                        .push(VariableExpression::MemberLocation(location));
+                }
+
+                let base_offset = match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(AttributeValue::UnitRef(offset)) => strip_type_aliases(unit, offset)?,
+                    _ => None,
+                };
+                if let Some(offset) = base_offset {
+                    let mut tree = unit.entries_tree(Some(offset))?;
+                    let root = tree.root()?;
+                    structure_members(
+                        root, dwarf, unit, &base, variables, group_id, lazy, visited,
                     )?;
                 }
             }
+            // every variant of a Rust enum is a group of its own, the active one is
+            // shown by the value of the enum itself. The discriminant is a member of
+            // the variant part and isn't listed
+            gimli::DW_TAG_variant_part => {
+                let mut variant_nodes = child.children();
+                while let Some(variant) = variant_nodes.next()? {
+                    if variant.entry().tag() == gimli::DW_TAG_variant {
+                        variant_group(
+                            variant,
+                            dwarf,
+                            unit,
+                            parent_variable,
+                            variables,
+                            group_id,
+                            lazy,
+                            visited,
+                        )?;
+                    }
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    Ok(())
+}
+
+/// List a variant of a Rust enum as a group named after it. rustc gives a variant a
+/// single member named after the variant, which is that group already; the members
+/// of other variants are put in a group named after the discriminant value
+#[allow(clippy::too_many_arguments)]
+fn variant_group(
+    node: gimli::EntriesTreeNode<DwarfReader>,
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader>,
+    parent_variable: &SymbolVariable,
+    variables: &mut Vec<SymbolVariable>,
+    group_id: &mut i32,
+    lazy: bool,
+    visited: &mut Vec<usize>,
+) -> Result<()> {
+    let offset = node.entry().offset();
+    let discr_value =
+        node.entry()
+            .attr_value(gimli::DW_AT_discr_value)?
+            .and_then(|attr| match attr {
+                AttributeValue::Sdata(value) => Some(value.to_string()),
+                attr => attr.udata_value().map(|value| value.to_string()),
+            });
+    let mut member_types = Vec::new();
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let mut members = tree.root()?.children();
+    while let Some(member) = members.next()? {
+        if member.entry().tag() == gimli::DW_TAG_member {
+            member_types.push(match member.entry().attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(ty)) => strip_type_aliases(unit, ty)?,
+                _ => None,
+            });
+        }
+    }
+
+    if let [Some(ty)] = member_types[..] {
+        if unit.entry(ty)?.tag() == gimli::DW_TAG_structure_type {
+            return structure_members(
+                node,
+                dwarf,
+                unit,
+                parent_variable,
+                variables,
+                group_id,
+                lazy,
+                visited,
+            );
         }
     }
 
+    let name = match discr_value {
+        Some(value) => format!("<variant {}>", value),
+        None => String::from("<default variant>"),
+    };
+    let mut var = SymbolVariable {
+        display_name: Some(format!(
+            "{}.{}",
+            parent_variable
+                .name
+                .as_ref()
+                .unwrap_or(&"<unnamed>".to_string()),
+            name
+        )),
+        name: Some(name),
+        contents: parent_variable.contents.clone(),
+        ty_offset: TypeDescripter::Description(String::from("variant")),
+        group_id: parent_variable.child_group_id.unwrap_or_default(),
+        child_group_id: Some(*group_id),
+        die_offset: offset.0,
+        ..Default::default()
+    };
+    *group_id += 1;
+
+    if lazy {
+        var.members_offset = Some(offset.0);
+    } else {
+        structure_members(node, dwarf, unit, &var, variables, group_id, false, visited)?;
+    }
+    variables.push(var);
     Ok(())
 }
 
@@ -281,26 +768,89 @@ fn transform_variable(
         content = Some(VariableExpression::Location(location));
         has_explicit_location = true;
     } else if let Some(location) = entry.attr_value(gimli::DW_AT_data_member_location)? {
-        content = Some(VariableExpression::Location(location));
+        content = Some(VariableExpression::MemberLocation(location));
         has_explicit_location = true;
     }
     if let Some(constant) = entry.attr_value(gimli::DW_AT_const_value)? {
         if !has_explicit_location {
-            // TODO: support big endian
-            let bytes = match constant {
-                AttributeValue::Block(block) => block.to_slice()?.to_vec(),
-                AttributeValue::Data1(b) => vec![b],
-                AttributeValue::Data2(b) => b.to_le_bytes().to_vec(),
-                AttributeValue::Data4(b) => b.to_le_bytes().to_vec(),
-                AttributeValue::Data8(b) => b.to_le_bytes().to_vec(),
-                AttributeValue::Sdata(b) => b.to_le_bytes().to_vec(),
-                AttributeValue::Udata(b) => b.to_le_bytes().to_vec(),
-                AttributeValue::String(b) => b.to_slice()?.to_vec(),
-                _ => unimplemented!(),
+            // blocks and strings are already laid out in target byte order
+            let is_integer = !matches!(
+                constant,
+                AttributeValue::Block(_) | AttributeValue::String(_)
+            );
+            let bytes = match &constant {
+                AttributeValue::Block(block) => Some(block.to_slice()?.to_vec()),
+                AttributeValue::Data1(b) => Some(vec![*b]),
+                AttributeValue::Data2(b) => Some(b.to_le_bytes().to_vec()),
+                AttributeValue::Data4(b) => Some(b.to_le_bytes().to_vec()),
+                AttributeValue::Data8(b) => Some(b.to_le_bytes().to_vec()),
+                AttributeValue::Sdata(b) => Some(b.to_le_bytes().to_vec()),
+                AttributeValue::Udata(b) => Some(b.to_le_bytes().to_vec()),
+                AttributeValue::String(b) => Some(b.to_slice()?.to_vec()),
+                _ => None,
+            };
+            // integer forms are as wide as the form, not the type, e.g. Sdata(-1)
+            // of a 4 bytes enum
+            let type_size = match entry.attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(offset)) if is_integer => {
+                    element_byte_size(unit, offset).ok()
+                }
+                _ => None,
+            };
+            content = Some(match bytes {
+                Some(mut bytes) => {
+                    if let Some(type_size) = type_size {
+                        bytes.truncate(std::cmp::max(type_size as usize, 1));
+                    }
+                    if is_integer && reader_byte_order(dwarf).is_big_endian() {
+                        bytes.reverse();
+                    }
+                    VariableExpression::ConstValue(bytes)
+                }
+                // keep the rest of the variables listed, evaluation reports the form
+                None => VariableExpression::Unknown {
+                    debug_info: format!("unsupported const_value {:?}", constant),
+                },
+            });
+        }
+    }
+    let bit_size = entry
+        .attr_value(gimli::DW_AT_bit_size)?
+        .and_then(|attr| attr.udata_value());
+    let mut bit_offset = None;
+    if let Some(bit_size) = bit_size {
+        if let Some(data_bit_offset) = entry
+            .attr_value(gimli::DW_AT_data_bit_offset)?
+            .and_then(|attr| attr.udata_value())
+        {
+            // DWARF5: bits from the beginning of the containing entity
+            content = Some(VariableExpression::MemberLocation(AttributeValue::Udata(
+                data_bit_offset / 8,
+            )));
+            bit_offset = Some(data_bit_offset % 8);
+        } else if let Some(msb_offset) = entry
+            .attr_value(gimli::DW_AT_bit_offset)?
+            .and_then(|attr| attr.udata_value())
+        {
+            // DWARF4: bits from the most significant bit of the storage unit
+            let storage_size = match entry
+                .attr_value(gimli::DW_AT_byte_size)?
+                .and_then(|attr| attr.udata_value())
+            {
+                Some(size) => Some(size),
+                None => match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(AttributeValue::UnitRef(offset)) => type_layout(unit, offset)?.0,
+                    _ => None,
+                },
             };
-            content = Some(VariableExpression::ConstValue(bytes));
+            if let Some(storage_size) = storage_size {
+                bit_offset = Some((storage_size * 8).saturating_sub(msb_offset + bit_size));
+            }
+        } else {
+            bit_offset = Some(0);
         }
     }
+
     let name = match entry.attr_value(gimli::DW_AT_name)? {
         Some(name_attr) => Some(clone_string_attribute(dwarf, unit, name_attr)?),
         None => None,
@@ -311,6 +861,18 @@ fn transform_variable(
         _ => TypeDescripter::Description(String::from("<unnamed>")),
     };
 
+    let decl_file = match entry.attr_value(gimli::DW_AT_decl_file)? {
+        Some(AttributeValue::FileIndex(index)) => file_index_path(dwarf, unit, index)?,
+        Some(attr) => match attr.udata_value() {
+            Some(index) => file_index_path(dwarf, unit, index)?,
+            None => None,
+        },
+        None => None,
+    };
+    let decl_line = entry
+        .attr_value(gimli::DW_AT_decl_line)?
+        .and_then(|attr| attr.udata_value());
+
     Ok(SymbolVariable {
         name: name.clone(),
         display_name: name,
@@ -320,7 +882,13 @@ fn transform_variable(
         },
         ty_offset: ty,
         group_id,
-        child_group_id: None,
+        die_offset: entry.offset().0,
+        bit_offset,
+        bit_size,
+        is_parameter: entry.tag() == gimli::DW_TAG_formal_parameter,
+        decl_file,
+        decl_line,
+        ..Default::default()
     })
 }
 
@@ -338,71 +906,158 @@ fn transform_namespace(
     Ok(SymbolVariable {
         name: name.clone(),
         display_name: name,
-        contents: vec![],
         ty_offset: TypeDescripter::Description(String::from("namespace")),
         group_id,
-        child_group_id: None,
+        die_offset: entry.offset().0,
+        ..Default::default()
     })
 }
 
+/// Locals, globals and operand stack of the wasm frame, for variables located by
+/// DW_OP_WASM_location
+#[derive(Clone, Copy)]
+pub struct WasmFrameValues<'a> {
+    pub locals: &'a WasmValueVector,
+    pub globals: &'a WasmValueVector,
+    pub stacks: &'a WasmValueVector,
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn evaluate_variable_from_string(
-    name: &String,
+    name: &str,
     variables: &Vec<SymbolVariable>,
     dwarf: &gimli::Dwarf<DwarfReader>,
     unit: &Unit<DwarfReader, DwarfReaderOffset>,
     frame_base: FrameBase,
+    values: WasmFrameValues,
+    code_offset: u64,
+    printers: &PrettyPrinters,
 ) -> Result<Option<VariableInfo>> {
     let name = name.replace("->", ".");
-    let this_name = format!("this.{}", name);
+    let (base_name, subscripts) = split_subscripts(&name)?;
+    let this_name = format!("this.{}", base_name);
 
-    let var = match variables
-        .iter()
-        .filter(|v| {
-            if let Some(ref vname) = v.display_name {
-                *vname == name || *vname == this_name
-            } else {
-                false
-            }
-        })
-        .next()
+    // locals shadow the members of this
+    let this_members;
+    let var = match find_variable(variables, base_name)
+        .or_else(|| find_variable(variables, &this_name))
     {
         Some(v) => v,
         None => {
-            return Err(anyhow!("'{}' is not valid variable name", name));
+            this_members = members_of_this(dwarf, unit, variables)?;
+            match find_variable(&this_members, &this_name) {
+                Some(v) => v,
+                None => {
+                    return Err(VariableNotFound(base_name.to_string()).into());
+                }
+            }
         }
     };
     let mut calculated_address = Vec::new();
     let mut constant_data = None;
+    let mut pieces = Vec::new();
+    let byte_order = reader_byte_order(dwarf);
 
     for content in &var.contents {
         match content {
-            VariableExpression::Location(location) => match location {
-                AttributeValue::Exprloc(expr) => {
-                    let piece =
-                        evaluate_variable_location(unit.encoding(), &frame_base, expr.clone())?;
-                    let piece = match piece.get(0) {
-                        Some(p) => p,
+            VariableExpression::Location(location) => {
+                let expr = match location {
+                    AttributeValue::Exprloc(expr) => expr.clone(),
+                    AttributeValue::LocationListsRef(_) | AttributeValue::DebugLocListsIndex(_) => {
+                        match location_list_expression(dwarf, unit, location, code_offset)? {
+                            Some(expr) => expr,
+                            None => {
+                                return Err(anyhow!(
+                                    "'{}' has no location at 0x{:x}",
+                                    name,
+                                    code_offset
+                                ));
+                            }
+                        }
+                    }
+                    AttributeValue::Sdata(b) => {
+                        calculated_address.push(VariableLocation::Offset(*b));
+                        continue;
+                    }
+                    // Udata and the Data1/2/4/8 constant forms are byte offsets
+                    location => match location.udata_value() {
+                        Some(b) => {
+                            calculated_address.push(VariableLocation::Offset(b as i64));
+                            continue;
+                        }
                         None => {
-                            println!("failed to get piece of variable");
-                            return Ok(None);
+                            return Ok(Some(described_variable_info(
+                                format!("<unsupported location {:?}>", location),
+                                dwarf,
+                            )));
                         }
-                    };
+                    },
+                };
 
-                    match piece.location {
-                        gimli::Location::Address { address } => {
-                            calculated_address.push(VariableLocation::Address(address));
-                        }
-                        _ => unimplemented!(),
-                    };
+                if is_wasm_location(&expr) {
+                    let (vector, kind, index) =
+                        match read_wasm_location(AttributeValue::Exprloc(expr))? {
+                            WasmLoc::Local(index) => (values.locals, "wasm local", index),
+                            WasmLoc::Global(index) => (values.globals, "wasm global", index),
+                            WasmLoc::Stack(index) => (values.stacks, "operand stack index", index),
+                        };
+                    let value = vector
+                        .data
+                        .get(index as usize)
+                        .ok_or_else(|| anyhow!("no value at {} {}", kind, index))?;
+                    constant_data = Some(value.value.to_le_bytes());
+                    continue;
                 }
-                AttributeValue::LocationListsRef(_listsref) => unimplemented!("listsref"),
-                AttributeValue::Sdata(b) => {
-                    calculated_address.push(VariableLocation::Offset(*b));
+
+                match evaluate_location_expression(unit.encoding(), &frame_base, expr, byte_order)?
+                {
+                    Some(PieceLocation::Memory(loc)) => calculated_address.push(loc),
+                    Some(PieceLocation::Value(bytes)) => constant_data = Some(bytes),
+                    Some(PieceLocation::Composite(layout)) => pieces = layout,
+                    Some(PieceLocation::Register(register)) => {
+                        return Ok(Some(described_variable_info(
+                            format!("<in register {}>", register),
+                            dwarf,
+                        )));
+                    }
+                    None => return Ok(None),
                 }
-                AttributeValue::Udata(b) => {
-                    calculated_address.push(VariableLocation::Offset(*b as i64));
+            }
+            VariableExpression::MemberLocation(location) => match location {
+                AttributeValue::Exprloc(expr) => {
+                    let member = VariableLocation::Member {
+                        expression: expr.clone(),
+                        encoding: unit.encoding(),
+                        reads: Vec::new(),
+                    };
+                    match resolved_address(&calculated_address) {
+                        // the parent is at a known address, the expression may refer to it
+                        Some(parent) => match evaluate_member_location(
+                            unit.encoding(),
+                            expr.clone(),
+                            parent,
+                            &[],
+                            byte_order,
+                        )? {
+                            MemberAddress::Resolved(address) => {
+                                calculated_address = vec![VariableLocation::Address(address)];
+                            }
+                            MemberAddress::RequiresMemory { .. } => calculated_address.push(member),
+                        },
+                        // the parent is behind a pointer, evaluated once it has been loaded
+                        None => calculated_address.push(member),
+                    }
                 }
-                _ => panic!(),
+                AttributeValue::Sdata(b) => calculated_address.push(VariableLocation::Offset(*b)),
+                location => match location.udata_value() {
+                    Some(b) => calculated_address.push(VariableLocation::Offset(b as i64)),
+                    None => {
+                        return Ok(Some(described_variable_info(
+                            format!("<unsupported member location {:?}>", location),
+                            dwarf,
+                        )));
+                    }
+                },
             },
             VariableExpression::ConstValue(ref _bytes) => {
                 constant_data = Some(_bytes.clone());
@@ -411,66 +1066,602 @@ pub fn evaluate_variable_from_string(
                 calculated_address.push(VariableLocation::Pointer);
             }
             VariableExpression::Unknown { ref debug_info } => {
-                unimplemented!("Unknown variable content found {}", debug_info)
+                return Ok(Some(described_variable_info(
+                    format!("<{}>", debug_info),
+                    dwarf,
+                )));
             }
         };
     }
 
     match &var.ty_offset {
+        TypeDescripter::TypeOffset(offset) if !subscripts.is_empty() => {
+            if !pieces.is_empty() {
+                return Err(anyhow!("variables split into pieces can't be subscripted"));
+            }
+            // bounds of variable length arrays are variables in scope, e.g. __vla_expr0
+            let resolve_bound = |die_offset: usize| -> Result<BoundValue> {
+                let bound_name = variables
+                    .iter()
+                    .find(|v| v.die_offset == die_offset)
+                    .and_then(|v| v.name.clone())
+                    .ok_or_else(|| anyhow!("array bound 0x{:x} is not in scope", die_offset))?;
+                let bound = evaluate_variable_from_string(
+                    &bound_name,
+                    variables,
+                    dwarf,
+                    unit,
+                    frame_base.clone(),
+                    values,
+                    code_offset,
+                    printers,
+                )?
+                .ok_or_else(|| anyhow!("array bound '{}' has no value here", bound_name))?;
+
+                if bound.address_expr.is_empty() {
+                    return match unsigned_from_bytes(&bound.memory_slice.memory_slice, byte_order)
+                        .to_u64_digits()
+                        .first()
+                    {
+                        Some(value) => Ok(BoundValue::Value(*value)),
+                        None if !bound.memory_slice.memory_slice.is_empty() => {
+                            Ok(BoundValue::Value(0))
+                        }
+                        None => Err(anyhow!("array bound '{}' has no value", bound_name)),
+                    };
+                }
+                match resolved_address(&bound.address_expr) {
+                    Some(address) => Ok(BoundValue::Memory {
+                        address,
+                        byte_size: bound.byte_size,
+                    }),
+                    None => Err(anyhow!("array bound '{}' behind a pointer", bound_name)),
+                }
+            };
+            let element_offset = apply_subscripts(
+                unit,
+                UnitOffset(*offset),
+                &subscripts,
+                &mut calculated_address,
+                &mut constant_data,
+                byte_order,
+                &resolve_bound,
+            )?;
+            let mut tree = unit.entries_tree(Some(element_offset))?;
+            let root = tree.root()?;
+
+            let mut x = create_variable_info(
+                root,
+                calculated_address,
+                constant_data,
+                dwarf,
+                unit,
+                printers,
+            )?;
+            x.variable_name = name;
+            Ok(Some(x))
+        }
         TypeDescripter::TypeOffset(offset) => {
             let mut tree = unit.entries_tree(Some(UnitOffset(*offset)))?;
             let root = tree.root()?;
 
-            return match create_variable_info(root, calculated_address, constant_data, dwarf, unit)
-            {
-                Ok(x) => Ok(Some(x)),
-                Err(e) => {
-                    console_log!("{}", e);
-                    Ok(None)
-                }
-            };
+            let mut x = create_variable_info(
+                root,
+                calculated_address,
+                constant_data,
+                dwarf,
+                unit,
+                printers,
+            )?;
+            if let Some(bit_size) = var.bit_size {
+                let bit_offset = var.bit_offset.unwrap_or(0);
+                // load every byte the bitfield touches
+                let bit_bytes = (bit_offset + bit_size).div_ceil(8) as usize;
+                x.byte_size = std::cmp::max(x.byte_size, bit_bytes);
+                x.bit_offset = Some(bit_offset);
+                x.bit_size = Some(bit_size);
+            }
+            if !pieces.is_empty() {
+                x.address_expr = Vec::new();
+                x.pieces = pieces;
+            }
+            x.variable_name = name;
+            Ok(Some(x))
+        }
+        TypeDescripter::Description(desc) => {
+            let mut x = described_variable_info(desc.clone(), dwarf);
+            x.variable_name = name;
+            Ok(Some(x))
         }
-        TypeDescripter::Description(desc) => Ok(Some(VariableInfo {
-            name: desc.clone(),
-            address_expr: Vec::new(),
-            byte_size: 0,
-            tag: gimli::DW_TAG_class_type,
-            memory_slice: MemorySlice::new(),
-            state: VariableEvaluationResult::Ready,
-            encoding: gimli::DW_ATE_ASCII,
-        })),
     }
 }
 
-fn evaluate_variable_location<R: gimli::Reader>(
-    encoding: gimli::Encoding,
-    base: &FrameBase,
-    expr: Expression<R>,
-) -> Result<Vec<gimli::Piece<R>>> {
-    let mut evaluation = expr.evaluation(encoding);
-    if let FrameBase::RBP(base) = base {
-        evaluation.set_initial_value(*base);
+/// Variable of the name, variables of inner blocks shadow the outer ones
+fn find_variable<'a>(variables: &'a [SymbolVariable], name: &str) -> Option<&'a SymbolVariable> {
+    variables
+        .iter()
+        .filter(|v| v.display_name.as_deref() == Some(name))
+        .min_by_key(|v| std::cmp::Reverse(v.scope_depth))
+}
+
+/// Members of the class pointed by `this` which are not listed yet, reached through
+/// the pointer when evaluated
+fn members_of_this(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader>,
+    variables: &[SymbolVariable],
+) -> Result<Vec<SymbolVariable>> {
+    let mut members = Vec::new();
+    let this = match find_variable(variables, "this") {
+        Some(x) => x,
+        None => return Ok(members),
+    };
+
+    if let Some(offset) = this.members_offset {
+        let mut tree = unit.entries_tree(Some(UnitOffset(offset)))?;
+        let root = tree.root()?;
+        let mut group_id = 0;
+        structure_members(
+            root,
+            dwarf,
+            unit,
+            this,
+            &mut members,
+            &mut group_id,
+            true,
+            &mut Vec::new(),
+        )?;
     }
-    let mut result = evaluation.evaluate()?;
-    use gimli::EvaluationResult;
-    loop {
+    Ok(members)
+}
+
+/// Split constant subscripts off the end of an expression, e.g. "a.b[1][2]" into "a.b" and [1, 2]
+fn split_subscripts(name: &str) -> Result<(&str, Vec<i64>)> {
+    let mut base = name.trim_end();
+    let mut subscripts = Vec::new();
+
+    while let Some(rest) = base.strip_suffix(']') {
+        let open = match rest.rfind('[') {
+            Some(x) => x,
+            None => return Err(anyhow!("unbalanced subscript in '{}'", name)),
+        };
+        let index = rest[open + 1..].trim();
+        let value = match index.strip_prefix("0x") {
+            Some(hex) => i64::from_str_radix(hex, 16),
+            None => index.parse::<i64>(),
+        };
+        match value {
+            Ok(x) => subscripts.insert(0, x),
+            Err(_) => return Err(anyhow!("subscript '{}' is not a constant integer", index)),
+        }
+        base = rest[..open].trim_end();
+    }
+
+    Ok((base, subscripts))
+}
+
+/// Append the element offsets of the subscripts to the location of an array or a pointer,
+/// and return the type of the element
+fn apply_subscripts(
+    unit: &Unit<DwarfReader>,
+    type_offset: UnitOffset<DwarfReaderOffset>,
+    subscripts: &[i64],
+    address: &mut Vec<VariableLocation>,
+    constant_data: &mut Option<Vec<u8>>,
+    byte_order: RunTimeEndian,
+    resolve_bound: &dyn Fn(usize) -> Result<BoundValue>,
+) -> Result<UnitOffset<DwarfReaderOffset>> {
+    // the dereferences appended for the pointer chain are redone per subscript
+    for _ in 0..pointer_depth(unit, type_offset)? {
+        if let Some(VariableLocation::Pointer) = address.last() {
+            address.pop();
+        }
+    }
+
+    let mut offset = type_offset;
+    // dimensions of the array being subscripted, outermost first
+    let mut dimensions: Vec<ArrayBound> = Vec::new();
+
+    for index in subscripts {
+        if dimensions.is_empty() {
+            let ty = match strip_type_aliases(unit, offset)? {
+                Some(x) => x,
+                None => return Err(anyhow!("void value can't be subscripted")),
+            };
+            let entry = unit.entry(ty)?;
+            offset = match entry.attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(x)) => x,
+                _ => return Err(anyhow!("element of void can't be subscripted")),
+            };
+
+            match entry.tag() {
+                gimli::DW_TAG_array_type => {
+                    if constant_data.is_some() {
+                        return Err(anyhow!("arrays out of linear memory can't be subscripted"));
+                    }
+                    dimensions = array_dimensions(unit, ty)?;
+                }
+                gimli::DW_TAG_pointer_type => {
+                    match constant_data.take() {
+                        // the pointer is held in a wasm local
+                        Some(bytes) => {
+                            let pointer = unsigned_from_bytes(&bytes, byte_order)
+                                .to_u64_digits()
+                                .first()
+                                .copied()
+                                .unwrap_or(0);
+                            *address = vec![VariableLocation::Address(pointer)];
+                        }
+                        None => address.push(VariableLocation::Pointer),
+                    }
+                    dimensions = vec![ArrayBound::Count(0)];
+                }
+                _ => return Err(anyhow!("only arrays and pointers can be subscripted")),
+            }
+        }
+
+        dimensions.remove(0);
+        let mut stride = element_byte_size(unit, offset)? as i64;
+        let mut runtime_bound = None;
+        for dimension in &dimensions {
+            match *dimension {
+                ArrayBound::Count(count) => stride *= count as i64,
+                ArrayBound::Variable { die_offset, bias } => {
+                    if runtime_bound.is_some() {
+                        return Err(anyhow!(
+                            "several runtime bounds in a stride are not supported"
+                        ));
+                    }
+                    runtime_bound = Some((resolve_bound(die_offset)?, bias));
+                }
+            }
+        }
+        address.push(match runtime_bound {
+            None => VariableLocation::Offset(index * stride),
+            Some((BoundValue::Value(count), bias)) => {
+                VariableLocation::Offset(index * stride * (count as i64 + bias))
+            }
+            // the bound is read by the evaluation
+            Some((
+                BoundValue::Memory {
+                    address: bound_address,
+                    byte_size,
+                },
+                bias,
+            )) => VariableLocation::Scaled {
+                address: bound_address,
+                byte_size,
+                bias,
+                scale: index * stride,
+            },
+        });
+    }
+
+    if !dimensions.is_empty() {
+        return Err(anyhow!("inner dimensions of the array need subscripts"));
+    }
+
+    Ok(offset)
+}
+
+/// Element count of an array dimension
+#[derive(Clone, Copy)]
+enum ArrayBound {
+    Count(u64),
+    /// DW_AT_count or DW_AT_upper_bound referring to the variable which holds the bound,
+    /// as for variable length arrays. The count is its value + bias
+    Variable {
+        die_offset: usize,
+        bias: i64,
+    },
+}
+
+impl ArrayBound {
+    /// The count known without evaluation, None for bounds held in variables which are
+    /// only read by the evaluation
+    fn constant(self) -> Option<u64> {
+        match self {
+            ArrayBound::Count(count) => Some(count),
+            ArrayBound::Variable { .. } => None,
+        }
+    }
+}
+
+/// Value of the variable holding a runtime array bound
+enum BoundValue {
+    Value(u64),
+    Memory { address: u64, byte_size: usize },
+}
+
+/// Element counts of DW_TAG_subrange_type children of an array, 0 for unknown bounds
+fn array_dimensions<R: gimli::Reader>(
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+) -> Result<Vec<ArrayBound>> {
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let root = tree.root()?;
+    let mut children = root.children();
+    let mut dimensions = Vec::new();
+
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        if entry.tag() != gimli::DW_TAG_subrange_type {
+            continue;
+        }
+
+        let count = match entry.attr_value(gimli::DW_AT_count)? {
+            Some(AttributeValue::UnitRef(offset)) => Some(ArrayBound::Variable {
+                die_offset: offset.0.into_u64() as usize,
+                bias: 0,
+            }),
+            Some(attr) => attr.udata_value().map(ArrayBound::Count),
+            None => None,
+        };
+        let count = match count {
+            Some(count) => count,
+            None => {
+                let lower = entry
+                    .attr_value(gimli::DW_AT_lower_bound)?
+                    .and_then(|attr| attr.udata_value())
+                    .unwrap_or(0);
+                match entry.attr_value(gimli::DW_AT_upper_bound)? {
+                    Some(AttributeValue::UnitRef(offset)) => ArrayBound::Variable {
+                        die_offset: offset.0.into_u64() as usize,
+                        bias: 1 - lower as i64,
+                    },
+                    Some(attr) => match attr.udata_value() {
+                        Some(upper) => ArrayBound::Count((upper + 1).saturating_sub(lower)),
+                        None => ArrayBound::Count(0),
+                    },
+                    None => ArrayBound::Count(0),
+                }
+            }
+        };
+        dimensions.push(count);
+    }
+
+    if dimensions.is_empty() {
+        dimensions.push(ArrayBound::Count(0));
+    }
+    Ok(dimensions)
+}
+
+/// Size of an array element
+fn element_byte_size<R: gimli::Reader>(
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+) -> Result<u64> {
+    type_layout(unit, offset)?
+        .0
+        .ok_or_else(|| anyhow!("size of the element is unknown"))
+}
+
+/// VariableInfo which evaluates to a fixed description instead of a value
+fn described_variable_info<R: gimli::Reader>(
+    description: String,
+    dwarf: &gimli::Dwarf<R>,
+) -> VariableInfo {
+    VariableInfo::new(
+        description,
+        gimli::DW_TAG_class_type,
+        gimli::DW_ATE_ASCII,
+        reader_byte_order(dwarf),
+    )
+}
+
+/// Find the location expression of a location list which covers code_offset. The list is
+/// either referenced by offset or, in DWARF 5, by an index into .debug_loclists
+fn location_list_expression(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader, DwarfReaderOffset>,
+    location: &AttributeValue<DwarfReader>,
+    code_offset: u64,
+) -> Result<Option<Expression<DwarfReader>>> {
+    let mut locations = match dwarf.attr_locations(unit, location.clone())? {
+        Some(locations) => locations,
+        None => return Ok(None),
+    };
+    while let Some(entry) = locations.next()? {
+        if (entry.range.begin..entry.range.end).contains(&code_offset) {
+            return Ok(Some(entry.data));
+        }
+    }
+    Ok(None)
+}
+
+/// Where the evaluated location expression placed the variable
+enum PieceLocation {
+    Memory(VariableLocation),
+    /// DW_OP_stack_value: the value itself, laid out in target byte order
+    Value(Vec<u8>),
+    Register(u16),
+    /// Several pieces which are concatenated in order
+    Composite(Vec<VariablePiece>),
+}
+
+/// Address of the object described by a VariableLocation chain, None when a
+/// pointer has to be read first
+fn resolved_address(locations: &[VariableLocation]) -> Option<u64> {
+    let mut address = None;
+    for location in locations {
+        match location {
+            VariableLocation::Address(addr) => address = Some(*addr),
+            VariableLocation::Offset(off) => address = address.map(|a| (a as i64 + off) as u64),
+            VariableLocation::Pointer
+            | VariableLocation::Scaled { .. }
+            | VariableLocation::Member { .. } => return None,
+        }
+    }
+    address
+}
+
+pub(crate) enum MemberAddress {
+    Resolved(u64),
+    /// The expression loads memory which isn't in the reads yet
+    RequiresMemory {
+        address: u64,
+        byte_size: usize,
+    },
+}
+
+/// Evaluate a DW_AT_data_member_location exprloc with the containing object's
+/// address pushed on the stack. The loads of the expression are answered from reads
+pub(crate) fn evaluate_member_location(
+    encoding: gimli::Encoding,
+    expr: Expression<DwarfReader>,
+    parent: u64,
+    reads: &[Vec<u8>],
+    byte_order: RunTimeEndian,
+) -> Result<MemberAddress> {
+    let mut evaluation = expr.evaluation(encoding);
+    evaluation.set_initial_value(parent);
+
+    let mut reads = reads.iter();
+    let mut result = evaluation.evaluate()?;
+    loop {
+        match result {
+            gimli::EvaluationResult::Complete => break,
+            gimli::EvaluationResult::RequiresMemory { address, size, .. } => {
+                let bytes = match reads.next() {
+                    Some(bytes) => bytes,
+                    None => {
+                        return Ok(MemberAddress::RequiresMemory {
+                            address,
+                            byte_size: size as usize,
+                        })
+                    }
+                };
+                let value = unsigned_from_bytes(bytes, byte_order)
+                    .to_u64_digits()
+                    .first()
+                    .copied()
+                    .unwrap_or(0);
+                result = evaluation.resume_with_memory(gimli::Value::Generic(value))?;
+            }
+            x => return Err(anyhow!("unsupported member location: {:?}", x)),
+        }
+    }
+
+    match evaluation.result().first().map(|piece| &piece.location) {
+        Some(gimli::Location::Address { address }) => Ok(MemberAddress::Resolved(*address)),
+        Some(gimli::Location::Value { value }) => Ok(MemberAddress::Resolved(value.to_u64(!0)?)),
+        x => Err(anyhow!("unsupported member location: {:?}", x)),
+    }
+}
+
+fn evaluate_location_expression(
+    encoding: gimli::Encoding,
+    frame_base: &FrameBase,
+    expr: Expression<DwarfReader>,
+    byte_order: RunTimeEndian,
+) -> Result<Option<PieceLocation>> {
+    let pieces = evaluate_variable_location(encoding, frame_base, expr)?;
+    if pieces.len() > 1 {
+        return composite_location(&pieces, byte_order).map(Some);
+    }
+
+    let piece = match pieces.first() {
+        Some(p) => p,
+        None => {
+            console_log!("failed to get piece of variable");
+            return Ok(None);
+        }
+    };
+
+    match piece.location {
+        gimli::Location::Address { address } => Ok(Some(PieceLocation::Memory(
+            VariableLocation::Address(address),
+        ))),
+        gimli::Location::Value { value } => {
+            Ok(Some(PieceLocation::Value(value_bytes(value, byte_order))))
+        }
+        gimli::Location::Register { register } => Ok(Some(PieceLocation::Register(register.0))),
+        ref x => Err(anyhow!("unsupported variable location: {:?}", x)),
+    }
+}
+
+fn composite_location(
+    pieces: &[gimli::Piece<DwarfReader>],
+    byte_order: RunTimeEndian,
+) -> Result<PieceLocation> {
+    let mut layout = Vec::new();
+
+    for piece in pieces {
+        let byte_size = match piece.size_in_bits {
+            Some(bits) => bits.div_ceil(8) as usize,
+            None => return Err(anyhow!("composite location has a piece without size")),
+        };
+
+        layout.push(match piece.location {
+            gimli::Location::Address { address } => VariablePiece::Memory { address, byte_size },
+            gimli::Location::Value { value } => {
+                let mut bytes = value_bytes(value, byte_order);
+                bytes.resize(byte_size, 0);
+                VariablePiece::Value(bytes)
+            }
+            gimli::Location::Register { register } => {
+                return Ok(PieceLocation::Register(register.0));
+            }
+            // the optimized out part of the variable
+            gimli::Location::Empty => VariablePiece::Value(vec![0; byte_size]),
+            ref x => return Err(anyhow!("unsupported variable location: {:?}", x)),
+        });
+    }
+
+    Ok(PieceLocation::Composite(layout))
+}
+
+fn value_bytes(value: gimli::Value, byte_order: RunTimeEndian) -> Vec<u8> {
+    let mut bytes = match value {
+        gimli::Value::Generic(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::I8(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::U8(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::I16(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::U16(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::I32(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::U32(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::I64(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::U64(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::F32(v) => v.to_le_bytes().to_vec(),
+        gimli::Value::F64(v) => v.to_le_bytes().to_vec(),
+    };
+    if byte_order.is_big_endian() {
+        bytes.reverse();
+    }
+    bytes
+}
+
+fn evaluate_variable_location<R: gimli::Reader>(
+    encoding: gimli::Encoding,
+    base: &FrameBase,
+    expr: Expression<R>,
+) -> Result<Vec<gimli::Piece<R>>> {
+    let mut evaluation = expr.evaluation(encoding);
+    if let FrameBase::RBP { rbp, .. } = base {
+        evaluation.set_initial_value(*rbp);
+    }
+    let mut result = evaluation.evaluate()?;
+    use gimli::EvaluationResult;
+    loop {
         if let EvaluationResult::Complete = result {
             return Ok(evaluation.result());
         }
         match result {
-            EvaluationResult::RequiresFrameBase => {
-                if let FrameBase::WasmFrameBase(base) = base {
-                    result = evaluation.resume_with_frame_base(*base)?;
-                } else {
+            EvaluationResult::RequiresFrameBase => match base {
+                FrameBase::WasmFrameBase { frame_base, .. } => {
+                    result = evaluation.resume_with_frame_base(*frame_base)?;
+                }
+                // DW_OP_fbreg of a subprogram without DW_AT_frame_base
+                FrameBase::RBP { rbp, .. } => {
+                    result = evaluation.resume_with_frame_base(*rbp)?;
+                }
+                FrameBase::WasmDataBase(_) => {
                     return Err(anyhow!("unexpected occurrence of DW_AT_frame_base"));
                 }
-            }
+            },
             EvaluationResult::RequiresRelocatedAddress(addr) => {
-                if let FrameBase::WasmDataBase(base) = base {
-                    result = evaluation.resume_with_relocated_address(addr + *base)?;
-                } else {
-                    return Err(anyhow!("unexpected occurrence of relocated_address"));
-                }
+                // DW_OP_addr of globals and static locals, relative to the data segment
+                result = evaluation.resume_with_relocated_address(addr + base.data_base())?;
             }
             ref x => Err(anyhow!("{:?}", x))?,
         }
@@ -483,8 +1674,10 @@ fn create_variable_info<R: gimli::Reader>(
     const_data: Option<Vec<u8>>,
     dwarf: &gimli::Dwarf<R>,
     unit: &Unit<R>,
+    printers: &PrettyPrinters,
 ) -> Result<VariableInfo> {
     let data = const_data.unwrap_or_default();
+    let byte_order = reader_byte_order(dwarf);
 
     match node.entry().tag() {
         gimli::DW_TAG_base_type => {
@@ -493,10 +1686,6 @@ fn create_variable_info<R: gimli::Reader>(
                 Some(attr) => clone_string_attribute(dwarf, unit, attr)?,
                 None => "<no type name>".to_string(),
             };
-            let byte_size = entry
-                .attr_value(gimli::DW_AT_byte_size)?
-                .and_then(|attr| attr.udata_value())
-                .unwrap_or(unit.header.address_size() as u64);
             let encoding = entry
                 .attr_value(gimli::DW_AT_encoding)?
                 .and_then(|attr| match attr {
@@ -504,15 +1693,28 @@ fn create_variable_info<R: gimli::Reader>(
                     _ => None,
                 })
                 .unwrap_or(gimli::constants::DW_ATE_unsigned);
+            let byte_size = entry
+                .attr_value(gimli::DW_AT_byte_size)?
+                .and_then(|attr| attr.udata_value())
+                .unwrap_or_else(|| default_base_type_size(unit, encoding));
+            let signed = matches!(encoding, gimli::DW_ATE_signed | gimli::DW_ATE_signed_char);
+            let data = extend_integer(data, byte_size as usize, signed, byte_order);
+            let fixed_scale = match (
+                entry.attr_value(gimli::DW_AT_binary_scale)?,
+                entry.attr_value(gimli::DW_AT_decimal_scale)?,
+            ) {
+                (Some(scale), _) => scale.sdata_value().map(FixedScale::Binary),
+                (None, Some(scale)) => scale.sdata_value().map(FixedScale::Decimal),
+                (None, None) => None,
+            };
 
             Ok(VariableInfo {
                 address_expr: address,
                 byte_size: byte_size as usize,
-                name,
-                encoding,
-                tag: gimli::DW_TAG_base_type,
                 memory_slice: MemorySlice::from_u8_vec(data),
-                state: VariableEvaluationResult::Ready,
+                pointer_size: unit.header.address_size() as usize,
+                fixed_scale,
+                ..VariableInfo::new(name, gimli::DW_TAG_base_type, encoding, byte_order)
             })
         }
         gimli::DW_TAG_class_type | gimli::DW_TAG_structure_type | gimli::DW_TAG_union_type => {
@@ -526,86 +1728,788 @@ fn create_variable_info<R: gimli::Reader>(
                 .attr_value(gimli::DW_AT_byte_size)?
                 .and_then(|attr| attr.udata_value())
                 .unwrap_or(0);
+            let pretty = match pretty_layout(dwarf, unit, entry.offset(), &type_name, printers)? {
+                Some(pretty) => Some(pretty),
+                None => variant_layout(dwarf, unit, entry.offset())?,
+            };
+
+            Ok(VariableInfo {
+                address_expr: address,
+                byte_size: byte_size as usize,
+                memory_slice: MemorySlice::from_u8_vec(data),
+                pointer_size: unit.header.address_size() as usize,
+                pretty,
+                children: Some(member_count(unit, entry.offset())?),
+                ..VariableInfo::new(type_name, tag, gimli::DW_ATE_signed, byte_order)
+            })
+        }
+        gimli::DW_TAG_enumeration_type => {
+            let entry = node.entry();
+            let type_name = match entry.attr_value(gimli::DW_AT_name)? {
+                Some(attr) => clone_string_attribute(dwarf, unit, attr)?,
+                None => "<no type name>".to_string(),
+            };
+            let byte_size = entry
+                .attr_value(gimli::DW_AT_byte_size)?
+                .and_then(|attr| attr.udata_value())
+                .unwrap_or(4);
+            let encoding = match entry.attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(offset)) => type_layout(unit, offset)?.1,
+                _ => None,
+            }
+            .unwrap_or(gimli::DW_ATE_signed);
+            let signed = matches!(encoding, gimli::DW_ATE_signed | gimli::DW_ATE_signed_char);
+            let data = extend_integer(data, byte_size as usize, signed, byte_order);
+
+            let mut enumerators = Vec::new();
+            let mut children = node.children();
+            while let Some(child) = children.next()? {
+                match child.entry().tag() {
+                    gimli::DW_TAG_enumerator => {
+                        let name = match child.entry().attr_value(gimli::DW_AT_name)? {
+                            Some(attr) => clone_string_attribute(dwarf, unit, attr)?,
+                            None => continue,
+                        };
+                        if let Some(value) = child
+                            .entry()
+                            .attr_value(gimli::DW_AT_const_value)?
+                            .and_then(|attr| enumerator_value(&attr, signed))
+                        {
+                            enumerators.push((name, value));
+                        }
+                    }
+                    _ => continue,
+                }
+            }
+
+            Ok(VariableInfo {
+                address_expr: address,
+                byte_size: byte_size as usize,
+                memory_slice: MemorySlice::from_u8_vec(data),
+                pointer_size: unit.header.address_size() as usize,
+                enumerators,
+                ..VariableInfo::new(
+                    type_name,
+                    gimli::DW_TAG_enumeration_type,
+                    encoding,
+                    byte_order,
+                )
+            })
+        }
+        gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => {
+            let entry = node.entry();
+            let tag = entry.tag();
+            let pointee_offset = match entry.attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(offset)) => Some(offset.0),
+                _ => None,
+            };
+            let pointee_name = unit_type_name(dwarf, unit, pointee_offset)
+                .unwrap_or_else(|_| "<no type name>".to_string());
+            // the name of a function type already reads as a pointer
+            let is_function_pointer = match pointee_offset {
+                Some(offset) => {
+                    unit.entry(UnitOffset(offset))?.tag() == gimli::DW_TAG_subroutine_type
+                }
+                None => false,
+            };
+            let byte_size = entry
+                .attr_value(gimli::DW_AT_byte_size)?
+                .and_then(|attr| attr.udata_value())
+                .unwrap_or(unit.header.address_size() as u64);
+
+            // The pointer itself is the value, so drop the dereferences
+            // which were appended for this pointer chain.
+            let mut address = address;
+            for _ in 0..pointer_depth(unit, entry.offset())? {
+                if let Some(VariableLocation::Pointer) = address.last() {
+                    address.pop();
+                }
+            }
+
+            let name = match tag {
+                gimli::DW_TAG_reference_type => format!("{} &", pointee_name),
+                _ if is_function_pointer => pointee_name,
+                _ => format!("{} *", pointee_name),
+            };
+
+            Ok(VariableInfo {
+                address_expr: address,
+                byte_size: byte_size as usize,
+                memory_slice: MemorySlice::from_u8_vec(data),
+                pointer_size: unit.header.address_size() as usize,
+                ..VariableInfo::new(name, tag, gimli::DW_ATE_address, byte_order)
+            })
+        }
+        // shown as the raw offset (or function pointer) the ABI stores
+        gimli::DW_TAG_ptr_to_member_type => {
+            let entry = node.entry();
+            let byte_size = entry
+                .attr_value(gimli::DW_AT_byte_size)?
+                .and_then(|attr| attr.udata_value())
+                .unwrap_or(unit.header.address_size() as u64);
+            let name = unit_type_name(dwarf, unit, Some(entry.offset().0))
+                .unwrap_or_else(|_| "<no type name>".to_string());
+            let data = extend_integer(data, byte_size as usize, true, byte_order);
 
             Ok(VariableInfo {
                 address_expr: address,
                 byte_size: byte_size as usize,
-                name: type_name,
-                encoding: gimli::DW_ATE_signed,
-                tag,
                 memory_slice: MemorySlice::from_u8_vec(data),
-                state: VariableEvaluationResult::Ready,
+                pointer_size: unit.header.address_size() as usize,
+                ..VariableInfo::new(
+                    name,
+                    gimli::DW_TAG_base_type,
+                    gimli::DW_ATE_signed,
+                    byte_order,
+                )
             })
         }
+        gimli::DW_TAG_typedef => match strip_type_aliases(unit, node.entry().offset())? {
+            Some(offset) => {
+                let mut tree = unit.entries_tree(Some(offset))?;
+                let root = tree.root()?;
+
+                create_variable_info(root, address, Some(data), dwarf, unit, printers)
+            }
+            None => Err(anyhow!("typedef of void has no value")),
+        },
+        // modifiers pass the value through to the inner type; a malformed one
+        // without it is shown by its name instead of failing the whole value
         _ => match node.entry().attr_value(gimli::DW_AT_type)? {
             Some(AttributeValue::UnitRef(ref offset)) => {
                 let mut tree = unit.entries_tree(Some(UnitOffset(offset.0)))?;
                 let root = tree.root()?;
 
-                create_variable_info(root, address, Some(data), dwarf, unit)
+                create_variable_info(root, address, Some(data), dwarf, unit, printers)
+            }
+            _ => {
+                let tag = node.entry().tag();
+                let name = match tag {
+                    gimli::DW_TAG_const_type => "const",
+                    gimli::DW_TAG_volatile_type => "volatile",
+                    gimli::DW_TAG_restrict_type => "restrict",
+                    gimli::DW_TAG_atomic_type => "_Atomic",
+                    _ => tag.static_string().unwrap_or("<unknown type>"),
+                };
+                console_log!(
+                    "{} at {:?} has no type, shown without a value",
+                    tag,
+                    node.entry().offset()
+                );
+                Ok(described_variable_info(name.to_string(), dwarf))
             }
-            _ => Err(anyhow!("unsupported DIE type")),
         },
     }
 }
 
-pub struct DwarfGlobalVariables {
-    pub dwarf_data: DwarfDebugData,
-}
+/// Widen a constant decoded from an up to 8 bytes form to the byte_size of its type,
+/// e.g. a u128 whose DW_AT_const_value is DW_FORM_udata
+fn extend_integer(
+    mut data: Vec<u8>,
+    byte_size: usize,
+    signed: bool,
+    byte_order: RunTimeEndian,
+) -> Vec<u8> {
+    if data.is_empty() || data.len() >= byte_size {
+        return data;
+    }
 
-impl DwarfGlobalVariables {
-    pub fn variable_name_list(
-        &self,
-        unit_offset: UnitSectionOffset,
-        root_id: i32,
-    ) -> Result<Vec<VariableName>> {
-        let (dwarf, unit) = match self.dwarf_data.unit_offset(unit_offset)? {
-            Some(x) => x,
-            None => {
-                return Ok(Vec::new());
-            }
-        };
+    let most_significant = if byte_order.is_big_endian() {
+        data[0]
+    } else {
+        data[data.len() - 1]
+    };
+    let fill = if signed && most_significant & 0x80 != 0 {
+        0xff
+    } else {
+        0
+    };
+    let padding = vec![fill; byte_size - data.len()];
 
-        let mut variables = variables_in_unit_entry(&dwarf, &unit, None, 0, root_id)?;
-        let list = variables
-            .iter_mut()
-            .map(|var| {
-                let mut v = VariableName {
-                    name: "<<not parsed yet>>".to_string(),
-                    display_name: "<<not parsed yet>>".to_string(),
-                    type_name: "<<not parsed yet>>".to_string(),
-                    group_id: var.group_id,
-                    child_group_id: var.child_group_id,
-                };
-                if let Some(ref mut name) = var.name {
-                    v.name = std::mem::take(name);
-                }
-                if let Some(ref mut display_name) = var.display_name {
-                    v.display_name = std::mem::take(display_name);
-                }
-                match &var.ty_offset {
-                    TypeDescripter::TypeOffset(offset) => {
-                        if let Ok(ty_name) = unit_type_name(&dwarf, &unit, Some(*offset)) {
-                            v.type_name = ty_name;
-                        }
-                    }
-                    TypeDescripter::Description(desc) => {
-                        v.type_name = desc.clone();
-                    }
-                }
+    if byte_order.is_big_endian() {
+        data.splice(0..0, padding);
+    } else {
+        data.extend(padding);
+    }
+    data
+}
 
-                v
-            })
-            .collect();
+/// Size of a base type without DW_AT_byte_size, guessed from its encoding
+fn default_base_type_size<R: gimli::Reader>(unit: &Unit<R>, encoding: gimli::DwAte) -> u64 {
+    match encoding {
+        gimli::DW_ATE_boolean | gimli::DW_ATE_signed_char | gimli::DW_ATE_unsigned_char => 1,
+        _ => unit.header.address_size() as u64,
+    }
+}
+
+/// Count pointer/reference types which structure_variable_recursive dereferences
+/// when following the type chain starting at offset
+fn pointer_depth<R: gimli::Reader>(unit: &Unit<R>, offset: UnitOffset<R::Offset>) -> Result<usize> {
+    let mut depth = 0;
+    let mut offset = offset;
 
-        Ok(list)
+    loop {
+        offset = match strip_type_aliases(unit, offset)? {
+            Some(offset) => offset,
+            None => return Ok(depth),
+        };
+        let entry = unit.entry(offset)?;
+        match entry.tag() {
+            gimli::DW_TAG_class_type
+            | gimli::DW_TAG_structure_type
+            | gimli::DW_TAG_union_type
+            | gimli::DW_TAG_ptr_to_member_type => {
+                return Ok(depth);
+            }
+            gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => depth += 1,
+            _ => {}
+        }
+        match entry.attr_value(gimli::DW_AT_type)? {
+            Some(AttributeValue::UnitRef(next)) if next != offset => offset = next,
+            _ => return Ok(depth),
+        }
+    }
+}
+
+/// Byte size and encoding of the value of a type, following typedefs and qualifiers
+fn type_layout<R: gimli::Reader>(
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+) -> Result<(Option<u64>, Option<gimli::DwAte>)> {
+    let offset = match strip_type_aliases(unit, offset)? {
+        Some(offset) => offset,
+        None => return Ok((None, None)),
+    };
+    let entry = unit.entry(offset)?;
+    let byte_size = entry
+        .attr_value(gimli::DW_AT_byte_size)?
+        .and_then(|attr| attr.udata_value());
+
+    match entry.tag() {
+        gimli::DW_TAG_base_type => {
+            let encoding = match entry.attr_value(gimli::DW_AT_encoding)? {
+                Some(AttributeValue::Encoding(encoding)) => Some(encoding),
+                _ => None,
+            };
+            let byte_size = byte_size.unwrap_or_else(|| {
+                default_base_type_size(unit, encoding.unwrap_or(gimli::DW_ATE_unsigned))
+            });
+            Ok((Some(byte_size), encoding))
+        }
+        gimli::DW_TAG_pointer_type | gimli::DW_TAG_reference_type => Ok((
+            Some(byte_size.unwrap_or(unit.header.address_size() as u64)),
+            Some(gimli::DW_ATE_address),
+        )),
+        gimli::DW_TAG_ptr_to_member_type => Ok((
+            Some(byte_size.unwrap_or(unit.header.address_size() as u64)),
+            Some(gimli::DW_ATE_signed),
+        )),
+        gimli::DW_TAG_enumeration_type => Ok((
+            byte_size,
+            match entry.attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(inner)) => type_layout(unit, inner)?.1,
+                _ => None,
+            },
+        )),
+        gimli::DW_TAG_array_type if byte_size.is_none() => {
+            let element = match entry.attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(element)) => element,
+                _ => return Err(anyhow!("array of void has no size")),
+            };
+            // variable length arrays have no size until their bound is read
+            let count = match array_dimensions(unit, offset)?
+                .into_iter()
+                .map(ArrayBound::constant)
+                .product::<Option<u64>>()
+            {
+                Some(count) => count,
+                None => return Ok((None, None)),
+            };
+            Ok((type_layout(unit, element)?.0.map(|size| count * size), None))
+        }
+        _ => Ok((byte_size, None)),
+    }
+}
+
+#[derive(Serialize)]
+struct TypeDescription {
+    type_name: String,
+    byte_size: Option<u64>,
+    members: Vec<TypeMember>,
+}
+
+#[derive(Serialize)]
+struct TypeMember {
+    name: String,
+    /// Byte offset from the start of the described type, None for static members
+    offset: Option<u64>,
+    byte_size: Option<u64>,
+    type_name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bit_size: Option<u64>,
+}
+
+/// Describe the type at type_offset as JSON: its name, size and members including
+/// the ones inherited from base classes
+pub fn type_description_json(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader>,
+    type_offset: UnitOffset<DwarfReaderOffset>,
+) -> Result<String> {
+    let mut variables = Vec::new();
+    if let Some(class) = strip_type_aliases(unit, type_offset)? {
+        let mut tree = unit.entries_tree(Some(class))?;
+        let root = tree.root()?;
+        structure_members(
+            root,
+            dwarf,
+            unit,
+            &SymbolVariable::default(),
+            &mut variables,
+            &mut 0,
+            true,
+            &mut Vec::new(),
+        )?;
+    }
+    let members = variables
+        .iter()
+        .map(|var| type_member(dwarf, unit, var))
+        .collect::<Result<Vec<_>>>()?;
+
+    let description = TypeDescription {
+        type_name: unit_type_name(dwarf, unit, Some(type_offset.0))?,
+        byte_size: type_layout(unit, type_offset)?.0,
+        members,
+    };
+    serde_json::to_string(&description).map_err(|e| anyhow!("failed to serialize: {}", e))
+}
+
+/// Follow typedefs and qualifiers to the underlying type, or None for void.
+/// The walks over type chains start from here
+fn strip_type_aliases<R: gimli::Reader>(
+    unit: &Unit<R>,
+    type_offset: UnitOffset<R::Offset>,
+) -> Result<Option<UnitOffset<R::Offset>>> {
+    let mut visited = Vec::new();
+    let mut offset = type_offset;
+
+    loop {
+        let entry = unit.entry(offset)?;
+        match entry.tag() {
+            gimli::DW_TAG_typedef
+            | gimli::DW_TAG_const_type
+            | gimli::DW_TAG_volatile_type
+            | gimli::DW_TAG_restrict_type
+            | gimli::DW_TAG_atomic_type => {}
+            _ => return Ok(Some(offset)),
+        }
+        if visited.contains(&offset) {
+            return Err(anyhow!("recursive type found at {:?}", offset));
+        }
+        visited.push(offset);
+
+        match entry.attr_value(gimli::DW_AT_type)? {
+            Some(AttributeValue::UnitRef(next)) => offset = next,
+            _ => return Ok(None),
+        }
+    }
+}
+
+/// Layout of a member listed by structure_members. The offset is the sum of its
+/// constant member locations, None for static members and members located by expressions
+fn type_member(
+    dwarf: &gimli::Dwarf<DwarfReader>,
+    unit: &Unit<DwarfReader>,
+    var: &SymbolVariable,
+) -> Result<TypeMember> {
+    let mut locations = var.contents.iter().filter_map(|content| match content {
+        VariableExpression::MemberLocation(AttributeValue::Sdata(location)) => {
+            Some(Some(*location as u64))
+        }
+        VariableExpression::MemberLocation(location) => Some(location.udata_value()),
+        _ => None,
+    });
+    let offset = match locations.next() {
+        Some(first) => locations.fold(first, |offset, location| Some(offset? + location?)),
+        None => None,
+    };
+
+    let ty = match var.ty_offset {
+        TypeDescripter::TypeOffset(offset) => Some(offset),
+        TypeDescripter::Description(_) => None,
+    };
+    Ok(TypeMember {
+        name: var
+            .name
+            .clone()
+            .unwrap_or_else(|| String::from("<unnamed>")),
+        offset,
+        byte_size: match ty {
+            Some(ty) => type_layout(unit, UnitOffset(ty))?.0,
+            None => None,
+        },
+        type_name: unit_type_name(dwarf, unit, ty)?,
+        bit_size: var.bit_size,
+    })
+}
+
+/// Resolve the members used by the first pretty printer matching type_name
+fn pretty_layout<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+    type_name: &str,
+    printers: &PrettyPrinters,
+) -> Result<Option<PrettyLayout>> {
+    for printer in printers.iter() {
+        if !printer.type_pattern.is_match(type_name) {
+            continue;
+        }
+
+        match &printer.kind {
+            PrettyPrinterKind::PointerLengthString { data, length } => {
+                let data = member_layout(dwarf, unit, offset, data)?;
+                let length = member_layout(dwarf, unit, offset, length)?;
+                if let (Some(data), Some(length)) = (data, length) {
+                    return Ok(Some(PrettyLayout::String { data, length }));
+                }
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Number of members of a class, base classes and Rust enum variants counting as one
+fn member_count<R: gimli::Reader>(unit: &Unit<R>, offset: UnitOffset<R::Offset>) -> Result<usize> {
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let root = tree.root()?;
+    let mut children = root.children();
+    let mut count = 0;
+
+    while let Some(child) = children.next()? {
+        match child.entry().tag() {
+            gimli::DW_TAG_member | gimli::DW_TAG_inheritance | gimli::DW_TAG_variant_part => {
+                count += 1
+            }
+            _ => {}
+        }
+    }
+    Ok(count)
+}
+
+/// Discriminant and variants of a Rust enum, None unless the class has a DW_TAG_variant_part
+/// whose active variant can be found
+fn variant_layout<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+) -> Result<Option<PrettyLayout>> {
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let root = tree.root()?;
+    let mut children = root.children();
+
+    while let Some(child) = children.next()? {
+        if child.entry().tag() != gimli::DW_TAG_variant_part {
+            continue;
+        }
+        // without DW_AT_discr the part holds a single variant, e.g. an enum of one
+        // variant, which is active whatever the value and is made the default
+        let discr_member = match child.entry().attr_value(gimli::DW_AT_discr)? {
+            Some(AttributeValue::UnitRef(member)) => Some(member),
+            _ => None,
+        };
+        let discriminant = if let Some(discr_member) = discr_member {
+            let member = unit.entry(discr_member)?;
+            let location = member
+                .attr_value(gimli::DW_AT_data_member_location)?
+                .and_then(|attr| attr.udata_value())
+                .unwrap_or(0);
+            let byte_size = match member.attr_value(gimli::DW_AT_type)? {
+                Some(AttributeValue::UnitRef(ty)) => type_layout(unit, ty)?.0,
+                _ => None,
+            };
+            match byte_size {
+                Some(byte_size) => MemberLayout {
+                    offset: location as usize,
+                    byte_size: byte_size as usize,
+                },
+                None => return Ok(None),
+            }
+        } else {
+            MemberLayout {
+                offset: 0,
+                byte_size: 0,
+            }
+        };
+
+        let mut variants = Vec::new();
+        let mut variant_nodes = child.children();
+        while let Some(variant) = variant_nodes.next()? {
+            if variant.entry().tag() != gimli::DW_TAG_variant {
+                continue;
+            }
+            let discr_value = variant
+                .entry()
+                .attr_value(gimli::DW_AT_discr_value)?
+                .and_then(|attr| match attr {
+                    AttributeValue::Sdata(value) => Some(value as u64),
+                    attr => attr.udata_value(),
+                });
+
+            // rustc names the single member of a variant after the variant, its type
+            // is a struct holding the fields of the variant
+            let mut name = String::from("<variant>");
+            let mut fields = Vec::new();
+            let mut members = variant.children();
+            while let Some(member) = members.next()? {
+                let entry = member.entry();
+                if entry.tag() != gimli::DW_TAG_member {
+                    continue;
+                }
+                if let Some(attr) = entry.attr_value(gimli::DW_AT_name)? {
+                    name = clone_string_attribute(dwarf, unit, attr)?;
+                }
+                let location = entry
+                    .attr_value(gimli::DW_AT_data_member_location)?
+                    .and_then(|attr| attr.udata_value())
+                    .unwrap_or(0);
+                let ty = match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(AttributeValue::UnitRef(ty)) => strip_type_aliases(unit, ty)?,
+                    _ => None,
+                };
+                if let Some(ty) = ty {
+                    if unit.entry(ty)?.tag() == gimli::DW_TAG_structure_type {
+                        variant_fields(dwarf, unit, ty, location, &mut fields)?;
+                    } else {
+                        fields.push(variant_field(dwarf, unit, name.clone(), ty, location)?);
+                    }
+                }
+                break;
+            }
+            variants.push(VariantName {
+                discr_value,
+                name,
+                fields,
+            });
+        }
+        // the active one of several variants can't be told apart, show the raw members
+        if discr_member.is_none() {
+            if variants.len() != 1 {
+                return Ok(None);
+            }
+            variants[0].discr_value = None;
+        }
+
+        return Ok(Some(PrettyLayout::Variant {
+            discriminant,
+            variants,
+        }));
+    }
+    Ok(None)
+}
+
+/// Fields of the struct of a variant which starts at base_offset of the enum
+fn variant_fields<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+    base_offset: u64,
+    fields: &mut Vec<VariantField>,
+) -> Result<()> {
+    let mut tree = unit.entries_tree(Some(offset))?;
+    let root = tree.root()?;
+    let mut children = root.children();
+
+    while let Some(child) = children.next()? {
+        let entry = child.entry();
+        if entry.tag() != gimli::DW_TAG_member {
+            continue;
+        }
+        let name = match entry.attr_value(gimli::DW_AT_name)? {
+            Some(attr) => clone_string_attribute(dwarf, unit, attr)?,
+            None => String::from("<unnamed>"),
+        };
+        let location = entry
+            .attr_value(gimli::DW_AT_data_member_location)?
+            .and_then(|attr| attr.udata_value())
+            .unwrap_or(0);
+        if let Some(AttributeValue::UnitRef(ty)) = entry.attr_value(gimli::DW_AT_type)? {
+            fields.push(variant_field(
+                dwarf,
+                unit,
+                name,
+                ty,
+                base_offset + location,
+            )?);
+        }
+    }
+    Ok(())
+}
+
+fn variant_field<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    name: String,
+    ty: UnitOffset<R::Offset>,
+    offset: u64,
+) -> Result<VariantField> {
+    let (byte_size, encoding) = type_layout(unit, ty)?;
+    Ok(VariantField {
+        name,
+        type_name: unit_type_name(dwarf, unit, Some(ty.0))?,
+        layout: MemberLayout {
+            offset: offset as usize,
+            byte_size: byte_size.unwrap_or(0) as usize,
+        },
+        encoding: byte_size.and(encoding),
+    })
+}
+
+/// Offset and size of a dotted member path like "vec.len" inside the class at offset
+fn member_layout<R: gimli::Reader>(
+    dwarf: &gimli::Dwarf<R>,
+    unit: &Unit<R>,
+    offset: UnitOffset<R::Offset>,
+    path: &str,
+) -> Result<Option<MemberLayout>> {
+    let mut class_offset = Some(offset);
+    let mut member_offset = 0;
+    let mut byte_size = None;
+
+    for name in path.split('.') {
+        let class = match class_offset {
+            Some(class) => class,
+            None => return Ok(None),
+        };
+        let mut tree = unit.entries_tree(Some(class))?;
+        let root = tree.root()?;
+        let mut children = root.children();
+
+        let mut member = None;
+        while let Some(child) = children.next()? {
+            let entry = child.entry();
+            if entry.tag() != gimli::DW_TAG_member {
+                continue;
+            }
+            let member_name = match entry.attr_value(gimli::DW_AT_name)? {
+                Some(attr) => clone_string_attribute(dwarf, unit, attr)?,
+                None => continue,
+            };
+            if member_name == name {
+                let location = entry
+                    .attr_value(gimli::DW_AT_data_member_location)?
+                    .and_then(|attr| attr.udata_value())
+                    .unwrap_or(0);
+                let ty = match entry.attr_value(gimli::DW_AT_type)? {
+                    Some(AttributeValue::UnitRef(ty)) => Some(ty),
+                    _ => None,
+                };
+                member = Some((location, ty));
+                break;
+            }
+        }
+
+        let (location, ty) = match member {
+            Some(member) => member,
+            None => return Ok(None),
+        };
+        member_offset += location as usize;
+        byte_size = match ty {
+            Some(ty) => type_layout(unit, ty)?.0,
+            None => None,
+        };
+        class_offset = match ty {
+            Some(ty) => strip_type_aliases(unit, ty)?,
+            None => None,
+        };
+    }
+
+    Ok(byte_size.map(|byte_size| MemberLayout {
+        offset: member_offset,
+        byte_size: byte_size as usize,
+    }))
+}
+
+/// Decode DW_AT_const_value of an enumerator, sign-extending fixed-size forms for signed enums
+fn enumerator_value<R: gimli::Reader>(attr: &AttributeValue<R>, signed: bool) -> Option<BigInt> {
+    match *attr {
+        AttributeValue::Data1(v) if signed => Some(BigInt::from(v as i8)),
+        AttributeValue::Data2(v) if signed => Some(BigInt::from(v as i16)),
+        AttributeValue::Data4(v) if signed => Some(BigInt::from(v as i32)),
+        AttributeValue::Data8(v) if signed => Some(BigInt::from(v as i64)),
+        AttributeValue::Sdata(v) => Some(BigInt::from(v)),
+        _ => attr.udata_value().map(BigInt::from),
+    }
+}
+
+pub struct DwarfGlobalVariables {
+    pub dwarf_data: DwarfDebugData,
+    pub lazy_groups: RefCell<LazyGroups>,
+}
+
+impl DwarfGlobalVariables {
+    pub fn variable_name_list(
+        &self,
+        unit_offset: UnitSectionOffset,
+        root_id: i32,
+    ) -> Result<Vec<VariableName>> {
+        let (dwarf, unit) = match self.dwarf_data.unit_offset(unit_offset)? {
+            Some(x) => x,
+            None => {
+                return Ok(Vec::new());
+            }
+        };
+
+        let mut variables = variables_in_unit_entry(&dwarf, &unit, None, 0, root_id, true)?;
+        self.lazy_groups.borrow_mut().reset(&mut variables, root_id);
+
+        Ok(variable_names(&dwarf, &unit, &mut variables))
+    }
+
+    /// Members of a struct listed by variable_name_list, None for unknown groups
+    pub fn expand_group(
+        &self,
+        unit_offset: UnitSectionOffset,
+        group_id: i32,
+    ) -> Result<Option<Vec<VariableName>>> {
+        let (dwarf, unit) = match self.dwarf_data.unit_offset(unit_offset)? {
+            Some(x) => x,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        let variables = self
+            .lazy_groups
+            .borrow_mut()
+            .expand(&dwarf, &unit, group_id)?;
+        Ok(variables.map(|mut variables| variable_names(&dwarf, &unit, &mut variables)))
+    }
+
+    /// Member layout of the type at type_offset in the unit, as JSON
+    pub fn describe_type(
+        &self,
+        unit_offset: UnitSectionOffset,
+        type_offset: usize,
+    ) -> Result<Option<String>> {
+        let (dwarf, unit) = match self.dwarf_data.unit_offset(unit_offset)? {
+            Some(x) => x,
+            None => {
+                return Ok(None);
+            }
+        };
+
+        type_description_json(&dwarf, &unit, UnitOffset(type_offset)).map(Some)
     }
 
     fn display_variable(
         &self,
         unit_offset: UnitSectionOffset,
         frame_base: FrameBase,
-        name: &String,
+        globals: &WasmValueVector,
+        name: &str,
+        code_offset: u64,
+        printers: &PrettyPrinters,
     ) -> Result<Option<VariableInfo>> {
         let (dwarf, unit) = match self.dwarf_data.unit_offset(unit_offset)? {
             Some(x) => x,
@@ -613,18 +2517,1114 @@ impl DwarfGlobalVariables {
                 return Ok(None);
             }
         };
-        let variables = variables_in_unit_entry(&dwarf, &unit, None, 0, 0)?;
+        let variables = variables_in_unit_entry(&dwarf, &unit, None, 0, 0, false)?;
+        // globals of the unit have no frame, only wasm globals may hold them
+        let empty = WasmValueVector::new();
+        let values = WasmFrameValues {
+            locals: &empty,
+            globals,
+            stacks: &empty,
+        };
 
-        evaluate_variable_from_string(name, &variables, &dwarf, &unit, frame_base)
+        evaluate_variable_from_string(
+            name,
+            &variables,
+            &dwarf,
+            &unit,
+            frame_base,
+            values,
+            code_offset,
+            printers,
+        )
     }
 
     pub fn get_variable_info(
         &self,
-        opts: &String,
+        opts: &str,
         unit_offset: UnitSectionOffset,
         data_base: usize,
-        _globals: &WasmValueVector,
+        globals: &WasmValueVector,
+        code_offset: usize,
+        printers: &PrettyPrinters,
+    ) -> Result<Option<VariableInfo>> {
+        self.display_variable(
+            unit_offset,
+            FrameBase::WasmDataBase(data_base as u64),
+            globals,
+            opts,
+            code_offset as u64,
+            printers,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dwarf::test_utils::{
+        add_base_type, add_composite, add_member, add_named, add_typed, add_variable, encoding,
+        find_entry, first_unit, load_dwarf, write_sections, write_uleb128,
+    };
+    use crate::dwarf::wasm_bindings::WasmValue;
+    use crate::dwarf::Dwarf;
+    use gimli::write::{self, DwarfUnit};
+
+    /// Value of a constant of the base type, a type without DW_AT_byte_size when byte_size
+    /// is None
+    fn format_constant(
+        name: &str,
+        base_encoding: gimli::DwAte,
+        byte_size: Option<u64>,
+        data: Vec<u8>,
+        byte_order: RunTimeEndian,
+    ) -> Option<String> {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let id = add_base_type(
+            &mut dwarf_unit.unit,
+            name,
+            base_encoding,
+            byte_size.unwrap_or(0),
+        );
+        if byte_size.is_none() {
+            dwarf_unit.unit.get_mut(id).delete(gimli::DW_AT_byte_size);
+        }
+
+        let dwarf = load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let mut tree = unit.entries_tree(None).unwrap();
+        let root = tree.root().unwrap();
+        let mut children = root.children();
+        let node = children.next().unwrap().unwrap();
+
+        let printers = PrettyPrinters::default();
+        let mut info =
+            create_variable_info(node, Vec::new(), Some(data), &dwarf, &unit, &printers).unwrap();
+        info.evaluate()
+    }
+
+    fn format_int(data: Vec<u8>, byte_order: RunTimeEndian) -> Option<String> {
+        format_constant("int", gimli::DW_ATE_signed, Some(4), data, byte_order)
+    }
+
+    #[test]
+    fn big_endian_values_are_decoded_in_reader_order() {
+        let bytes = vec![0x00, 0x00, 0x01, 0x02];
+        assert_eq!(
+            format_int(bytes.clone(), RunTimeEndian::Big),
+            Some("(int)258".to_string())
+        );
+        assert_eq!(
+            format_int(bytes, RunTimeEndian::Little),
+            Some("(int)33619968".to_string())
+        );
+    }
+
+    /// Unit with three globals of struct Outer { struct Inner { int b; } inner; int a; }
+    fn nested_struct_unit() -> Dwarf {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let inner = add_composite(unit, gimli::DW_TAG_structure_type, "Inner", 4);
+        add_member(unit, inner, "b", int, None);
+
+        let outer = add_composite(unit, gimli::DW_TAG_structure_type, "Outer", 8);
+        for (member, ty, offset) in [("inner", inner, 0), ("a", int, 4)] {
+            add_member(
+                unit,
+                outer,
+                member,
+                ty,
+                Some(write::AttributeValue::Udata(offset)),
+            );
+        }
+
+        for variable in ["x", "y", "z"] {
+            add_variable(unit, variable, outer, None);
+        }
+
+        let byte_order = RunTimeEndian::Little;
+        load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order)
+    }
+
+    fn list(dwarf: &Dwarf, lazy: &mut LazyGroups, root_group_id: i32) -> Vec<SymbolVariable> {
+        let unit = first_unit(dwarf);
+        let mut variables =
+            variables_in_unit_entry(dwarf, &unit, None, 0, root_group_id, true).unwrap();
+        lazy.reset(&mut variables, root_group_id);
+        variables
+    }
+
+    fn expand(dwarf: &Dwarf, lazy: &mut LazyGroups, group_id: i32) -> Vec<SymbolVariable> {
+        let unit = first_unit(dwarf);
+        lazy.expand(dwarf, &unit, group_id).unwrap().unwrap()
+    }
+
+    fn names(variables: &[SymbolVariable]) -> Vec<&str> {
+        variables
+            .iter()
+            .map(|var| var.name.as_deref().unwrap_or_default())
+            .collect()
+    }
+
+    #[test]
+    fn truncated_groups_are_numbered_under_their_root() {
+        let dwarf = nested_struct_unit();
+
+        for (root_group_id, first_group_id) in [(1000, 10000), (1001, 20000)] {
+            let mut lazy = LazyGroups {
+                max_variables: Some(2),
+                ..Default::default()
+            };
+            let variables = list(&dwarf, &mut lazy, root_group_id);
+            assert_eq!(names(&variables), ["x", "y", "<truncated>"]);
+
+            let sentinel = variables[2].child_group_id.unwrap();
+            assert!(sentinel >= first_group_id && sentinel < first_group_id + 10000);
+            for var in &variables[..2] {
+                assert_eq!(var.group_id, root_group_id);
+                assert_ne!(var.child_group_id, Some(sentinel));
+            }
+
+            let rest = expand(&dwarf, &mut lazy, sentinel);
+            assert_eq!(names(&rest), ["z"]);
+            assert_eq!(rest[0].group_id, sentinel);
+        }
+    }
+
+    #[test]
+    fn groups_nested_too_deep_are_deferred() {
+        let dwarf = nested_struct_unit();
+        let mut lazy = LazyGroups {
+            max_depth: Some(2),
+            ..Default::default()
+        };
+        let variables = list(&dwarf, &mut lazy, 1000);
+        assert_eq!(names(&variables), ["x", "y", "z"]);
+
+        let members = expand(&dwarf, &mut lazy, variables[0].child_group_id.unwrap());
+        assert_eq!(names(&members), ["inner", "a"]);
+
+        let inner_group_id = members[0].child_group_id.unwrap();
+        let deferred = expand(&dwarf, &mut lazy, inner_group_id);
+        assert_eq!(names(&deferred), ["<truncated>"]);
+        assert_eq!(deferred[0].group_id, inner_group_id);
+
+        let deferred_group_id = deferred[0].child_group_id.unwrap();
+        let inner = expand(&dwarf, &mut lazy, deferred_group_id);
+        assert_eq!(names(&inner), ["b"]);
+        assert_eq!(inner[0].group_id, deferred_group_id);
+    }
+
+    #[test]
+    fn lexical_blocks_with_range_lists_scope_their_variables() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let root = unit.root();
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        add_variable(unit, "outer", int, None);
+
+        let ranges = unit.ranges.add(write::RangeList(
+            [(0x10, 0x20), (0x40, 0x50)]
+                .iter()
+                .map(|(begin, end)| write::Range::StartEnd {
+                    begin: write::Address::Constant(*begin),
+                    end: write::Address::Constant(*end),
+                })
+                .collect(),
+        ));
+        let block = unit.add(root, gimli::DW_TAG_lexical_block);
+        unit.get_mut(block).set(
+            gimli::DW_AT_ranges,
+            write::AttributeValue::RangeListRef(ranges),
+        );
+        let inner = add_named(unit, block, gimli::DW_TAG_variable, "inner");
+        unit.get_mut(inner)
+            .set(gimli::DW_AT_type, write::AttributeValue::UnitRef(int));
+
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        for (code_offset, expected) in [
+            (0x18, vec!["outer", "inner"]),
+            (0x48, vec!["outer", "inner"]),
+            (0x30, vec!["outer"]),
+        ] {
+            let variables =
+                variables_in_unit_entry(&dwarf, &unit, None, code_offset, 1000, false).unwrap();
+            assert_eq!(names(&variables), expected);
+        }
+    }
+
+    #[test]
+    fn virtual_base_members_are_located_through_memory() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let root = unit.root();
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let base = add_named(unit, root, gimli::DW_TAG_class_type, "Base");
+        add_member(unit, base, "b", int, None);
+
+        let derived = add_named(unit, root, gimli::DW_TAG_class_type, "Derived");
+        let inheritance = add_typed(unit, derived, gimli::DW_TAG_inheritance, base);
+        unit.get_mut(inheritance).set(
+            gimli::DW_AT_virtuality,
+            write::AttributeValue::Virtuality(gimli::DW_VIRTUALITY_virtual),
+        );
+        // the offset of the base is stored 12 bytes before the address point of the vtable
+        let offset_from_vtable = vec![
+            gimli::DW_OP_dup.0,
+            gimli::DW_OP_deref.0,
+            gimli::DW_OP_constu.0,
+            12,
+            gimli::DW_OP_minus.0,
+            gimli::DW_OP_deref.0,
+            gimli::DW_OP_plus.0,
+        ];
+        unit.get_mut(inheritance).set(
+            gimli::DW_AT_data_member_location,
+            write::AttributeValue::Exprloc(write::Expression::raw(offset_from_vtable)),
+        );
+
+        add_variable(unit, "obj", derived, Some(0x100));
+
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let variables = variables_in_unit_entry(&dwarf, &unit, None, 0, 0, false).unwrap();
+        let mut info = evaluate_variable_from_string(
+            "obj.b",
+            &variables,
+            &dwarf,
+            &unit,
+            FrameBase::WasmDataBase(0),
+            no_values(),
+            0,
+            &PrettyPrinters::default(),
+        )
+        .unwrap()
+        .unwrap();
+
+        // vptr of obj, then the offset of Base in the vtable, then b itself
+        let reads: [(u64, u32); 3] = [(0x100, 0x200), (0x1f4, 8), (0x108, 42)];
+        assert_eq!(info.evaluate(), None);
+        for (index, (address, value)) in reads.iter().enumerate() {
+            let slice = info.required_memory_slice();
+            assert_eq!(slice.address, *address);
+            assert_eq!(slice.byte_size, 4);
+
+            let mut memory = slice.clone();
+            memory.set_memory_slice(&value.to_le_bytes());
+            let result = info.resume_with_memory_slice(memory);
+            if index + 1 < reads.len() {
+                assert_eq!(result, None);
+            } else {
+                assert_eq!(result, Some("(int)42".to_string()));
+            }
+        }
+    }
+
+    #[test]
+    fn type_description_follows_aliases_and_bases() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let root = unit.root();
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let pair = add_typed(unit, root, gimli::DW_TAG_array_type, int);
+        let bound = unit.add(pair, gimli::DW_TAG_subrange_type);
+        unit.get_mut(bound)
+            .set(gimli::DW_AT_count, write::AttributeValue::Udata(2));
+
+        let base = add_composite(unit, gimli::DW_TAG_structure_type, "Base", 4);
+        add_member(unit, base, "x", int, None);
+
+        let derived = add_composite(unit, gimli::DW_TAG_structure_type, "Derived", 12);
+        let inheritance = add_typed(unit, derived, gimli::DW_TAG_inheritance, base);
+        unit.get_mut(inheritance).set(
+            gimli::DW_AT_data_member_location,
+            write::AttributeValue::Udata(8),
+        );
+        add_member(
+            unit,
+            derived,
+            "values",
+            pair,
+            Some(write::AttributeValue::Udata(0)),
+        );
+
+        let constant = add_typed(unit, root, gimli::DW_TAG_const_type, derived);
+        let alias = add_typed(unit, root, gimli::DW_TAG_typedef, constant);
+        unit.get_mut(alias).set(
+            gimli::DW_AT_name,
+            write::AttributeValue::String(b"D".to_vec()),
+        );
+
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let alias = find_entry(&unit, gimli::DW_TAG_typedef);
+
+        let json = type_description_json(&dwarf, &unit, alias.unwrap()).unwrap();
+        let description: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(description["type_name"], "D");
+        assert_eq!(description["byte_size"], 12);
+        let members = description["members"].as_array().unwrap();
+        let layout: Vec<_> = members
+            .iter()
+            .map(|member| {
+                (
+                    member["name"].as_str().unwrap(),
+                    member["offset"].as_u64(),
+                    member["byte_size"].as_u64(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            vec![("x", Some(8), Some(4)), ("values", Some(0), Some(8))]
+        );
+    }
+
+    /// An Option<int> like Rust enum of the (name, DW_AT_discr_value) variants, formatted
+    /// from the given bytes. Variants other than None hold an int at offset 4
+    fn format_enum(
+        variants: &[(&str, Option<u64>)],
+        discriminant: bool,
+        data: Vec<u8>,
+    ) -> Option<String> {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let option = add_composite(unit, gimli::DW_TAG_structure_type, "Option<int>", 8);
+        let part = unit.add(option, gimli::DW_TAG_variant_part);
+        if discriminant {
+            let discr = add_typed(unit, part, gimli::DW_TAG_member, int);
+            unit.get_mut(part)
+                .set(gimli::DW_AT_discr, write::AttributeValue::UnitRef(discr));
+        }
+
+        for (variant_name, discr_value) in variants {
+            let variant_type = add_named(unit, option, gimli::DW_TAG_structure_type, variant_name);
+            if *variant_name != "None" {
+                add_member(
+                    unit,
+                    variant_type,
+                    "__0",
+                    int,
+                    Some(write::AttributeValue::Udata(4)),
+                );
+            }
+
+            let variant = unit.add(part, gimli::DW_TAG_variant);
+            if let Some(discr_value) = discr_value {
+                unit.get_mut(variant).set(
+                    gimli::DW_AT_discr_value,
+                    write::AttributeValue::Udata(*discr_value),
+                );
+            }
+            add_member(unit, variant, variant_name, variant_type, None);
+        }
+
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let mut tree = unit.entries_tree(None).unwrap();
+        let root = tree.root().unwrap();
+        let mut children = root.children();
+        let mut node = children.next().unwrap().unwrap();
+        while node.entry().tag() != gimli::DW_TAG_structure_type {
+            node = children.next().unwrap().unwrap();
+        }
+
+        let printers = PrettyPrinters::default();
+        let mut info =
+            create_variable_info(node, Vec::new(), Some(data), &dwarf, &unit, &printers).unwrap();
+        info.evaluate()
+    }
+
+    fn format_option(data: Vec<u8>) -> Option<String> {
+        let variants = [("None", Some(0)), ("Some", Some(1)), ("Other", None)];
+        format_enum(&variants, true, data)
+    }
+
+    #[test]
+    fn rust_enums_show_the_fields_of_the_active_variant() {
+        assert_eq!(
+            format_option(vec![1, 0, 0, 0, 5, 0, 0, 0]),
+            Some("(Option<int>)Some { __0: (int)5 }".to_string())
+        );
+        assert_eq!(
+            format_option(vec![0, 0, 0, 0, 5, 0, 0, 0]),
+            Some("(Option<int>)None".to_string())
+        );
+        // values without a matching DW_AT_discr_value select the default variant
+        assert_eq!(
+            format_option(vec![7, 0, 0, 0, 0xff, 0xff, 0xff, 0xff]),
+            Some("(Option<int>)Other { __0: (int)-1 }".to_string())
+        );
+    }
+
+    #[test]
+    fn variant_parts_without_a_discriminant_show_their_only_variant() {
+        assert_eq!(
+            format_enum(&[("Some", Some(1))], false, vec![9, 0, 0, 0, 5, 0, 0, 0]),
+            Some("(Option<int>)Some { __0: (int)5 }".to_string())
+        );
+    }
+
+    /// Global `shape` of an enum whose variant 0 is laid out by rustc, a member named
+    /// Circle of a struct { int radius; }, and whose variant 1 holds the members w and h
+    fn shape_unit() -> Dwarf {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let shape = add_composite(unit, gimli::DW_TAG_structure_type, "Shape", 12);
+        let circle = add_composite(unit, gimli::DW_TAG_structure_type, "Circle", 12);
+        add_member(
+            unit,
+            circle,
+            "radius",
+            int,
+            Some(write::AttributeValue::Udata(4)),
+        );
+
+        let part = unit.add(shape, gimli::DW_TAG_variant_part);
+        let discr = add_member(unit, part, "tag", int, None);
+        unit.get_mut(part)
+            .set(gimli::DW_AT_discr, write::AttributeValue::UnitRef(discr));
+        let variant = unit.add(part, gimli::DW_TAG_variant);
+        unit.get_mut(variant)
+            .set(gimli::DW_AT_discr_value, write::AttributeValue::Udata(0));
+        add_member(unit, variant, "Circle", circle, None);
+        let variant = unit.add(part, gimli::DW_TAG_variant);
+        unit.get_mut(variant)
+            .set(gimli::DW_AT_discr_value, write::AttributeValue::Udata(1));
+        for (member, offset) in [("w", 4), ("h", 8)] {
+            add_member(
+                unit,
+                variant,
+                member,
+                int,
+                Some(write::AttributeValue::Udata(offset)),
+            );
+        }
+        add_variable(unit, "shape", shape, None);
+
+        let byte_order = RunTimeEndian::Little;
+        load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order)
+    }
+
+    #[test]
+    fn enum_variants_are_listed_as_groups() {
+        let dwarf = shape_unit();
+        let mut lazy = LazyGroups::default();
+        let variables = list(&dwarf, &mut lazy, 1000);
+        assert_eq!(names(&variables), ["shape"]);
+
+        let variants = expand(&dwarf, &mut lazy, variables[0].child_group_id.unwrap());
+        assert_eq!(names(&variants), ["Circle", "<variant 1>"]);
+        let circle = expand(&dwarf, &mut lazy, variants[0].child_group_id.unwrap());
+        assert_eq!(names(&circle), ["radius"]);
+        let members = expand(&dwarf, &mut lazy, variants[1].child_group_id.unwrap());
+        assert_eq!(names(&members), ["w", "h"]);
+        assert_eq!(members[1].display_name.as_deref(), Some("<variant 1>.h"));
+
+        // listed at once, every variable is in the group of its parent
+        let unit = first_unit(&dwarf);
+        let variables = variables_in_unit_entry(&dwarf, &unit, None, 0, 1000, false).unwrap();
+        let group_of = |name: &str| {
+            let var = variables
+                .iter()
+                .find(|var| var.name.as_deref() == Some(name))
+                .unwrap();
+            (var.group_id, var.child_group_id)
+        };
+        let shape_group = group_of("shape").1.unwrap();
+        let variant_group = group_of("<variant 1>");
+        assert_eq!(variant_group.0, shape_group);
+        assert_eq!(group_of("w").0, variant_group.1.unwrap());
+        assert_eq!(group_of("radius").0, group_of("Circle").1.unwrap());
+        assert!(variables
+            .iter()
+            .all(|var| var.name.as_deref() != Some("tag")));
+    }
+
+    /// Offsets appended by subscripting the array of the given dimensions, the runtime
+    /// bound of `n` is 5
+    fn subscript_offsets(dimensions: &[Option<u64>], subscripts: &[i64]) -> Vec<i64> {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let root = unit.root();
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let n = add_named(unit, root, gimli::DW_TAG_variable, "n");
+        let array = add_typed(unit, root, gimli::DW_TAG_array_type, int);
+        for dimension in dimensions {
+            let subrange = unit.add(array, gimli::DW_TAG_subrange_type);
+            let count = match dimension {
+                Some(count) => write::AttributeValue::Udata(*count),
+                None => write::AttributeValue::UnitRef(n),
+            };
+            unit.get_mut(subrange).set(gimli::DW_AT_count, count);
+        }
+
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let mut entries = unit.entries();
+        let (mut array, mut n) = (None, None);
+        while let Some((_, entry)) = entries.next_dfs().unwrap() {
+            match entry.tag() {
+                gimli::DW_TAG_array_type => array = Some(entry.offset()),
+                gimli::DW_TAG_variable => n = Some(entry.offset().0),
+                _ => {}
+            }
+        }
+
+        let resolve_bound = |die_offset: usize| -> Result<BoundValue> {
+            assert_eq!(Some(die_offset), n);
+            Ok(BoundValue::Value(5))
+        };
+        let mut address = vec![VariableLocation::Address(0x100)];
+        let element = apply_subscripts(
+            &unit,
+            array.unwrap(),
+            subscripts,
+            &mut address,
+            &mut None,
+            byte_order,
+            &resolve_bound,
+        )
+        .unwrap();
+        assert_eq!(unit.entry(element).unwrap().tag(), gimli::DW_TAG_base_type);
+
+        address
+            .iter()
+            .skip(1)
+            .map(|location| match location {
+                VariableLocation::Offset(offset) => *offset,
+                _ => panic!("only offsets are expected"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn nested_array_subscripts_scale_by_inner_dimensions() {
+        // int matrix[3][4]; matrix[2][1]
+        assert_eq!(subscript_offsets(&[Some(3), Some(4)], &[2, 1]), vec![32, 4]);
+        // int vla[2][n]; vla[1][3]
+        assert_eq!(subscript_offsets(&[Some(2), None], &[1, 3]), vec![20, 12]);
+        // int vla[n][4]; vla[3][2], the outer bound doesn't affect the strides
+        assert_eq!(subscript_offsets(&[None, Some(4)], &[3, 2]), vec![48, 8]);
+    }
+
+    #[test]
+    fn variable_length_arrays_have_no_size_until_evaluated() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let root = unit.root();
+
+        // int grid[2][n], n being a global at 0x200
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let n = add_variable(unit, "n", int, Some(0x200));
+        let grid = add_typed(unit, root, gimli::DW_TAG_array_type, int);
+        for count in [
+            write::AttributeValue::Udata(2),
+            write::AttributeValue::UnitRef(n),
+        ] {
+            let subrange = unit.add(grid, gimli::DW_TAG_subrange_type);
+            unit.get_mut(subrange).set(gimli::DW_AT_count, count);
+        }
+        add_variable(unit, "grid", grid, Some(0x100));
+        let rows = add_composite(unit, gimli::DW_TAG_structure_type, "Rows", 0);
+        add_member(unit, rows, "grid", grid, None);
+
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(&mut dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let rows = find_entry(&unit, gimli::DW_TAG_structure_type).unwrap();
+        let json = type_description_json(&dwarf, &unit, rows).unwrap();
+        let description: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let member = &description["members"][0];
+        assert_eq!(member["name"], "grid");
+        assert!(member["byte_size"].is_null());
+
+        // the bound is read before the element, grid[1][3] is at 0x100 + (n + 3) * 4
+        let (requests, result) = evaluate_with_memory(
+            &mut dwarf_unit,
+            "grid[1][3]",
+            &[(0x200, &5u32.to_le_bytes()), (0x100, &[7; 0x40])],
+        );
+        assert_eq!(requests, vec![(0x200, 4), (0x120, 4)]);
+        assert_eq!(result, Some("(int)117901063".to_string()));
+    }
+
+    #[test]
+    fn sizeless_booleans_are_one_byte() {
+        // a 4 byte default would fail to read the single byte
+        assert_eq!(
+            format_constant(
+                "bool",
+                gimli::DW_ATE_boolean,
+                None,
+                vec![1],
+                RunTimeEndian::Little
+            ),
+            Some("(bool)true".to_string())
+        );
+    }
+
+    /// Evaluate the expression, answering the memory requests from the blocks of memory.
+    /// Returns the (address, byte_size) of each request and the result
+    fn evaluate_with_memory(
+        dwarf_unit: &mut DwarfUnit,
+        expression: &str,
+        memory: &[(u64, &[u8])],
+    ) -> (Vec<(u64, usize)>, Option<String>) {
+        evaluate_in_frame(dwarf_unit, expression, FrameBase::WasmDataBase(0), memory)
+    }
+
+    fn evaluate_in_frame(
+        dwarf_unit: &mut DwarfUnit,
+        expression: &str,
+        frame_base: FrameBase,
+        memory: &[(u64, &[u8])],
+    ) -> (Vec<(u64, usize)>, Option<String>) {
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let variables = variables_in_unit_entry(&dwarf, &unit, None, 0, 0, false).unwrap();
+        let mut info = evaluate_variable_from_string(
+            expression,
+            &variables,
+            &dwarf,
+            &unit,
+            frame_base,
+            no_values(),
+            0,
+            &PrettyPrinters::default(),
+        )
+        .unwrap()
+        .unwrap();
+
+        let mut requests = Vec::new();
+        let mut result = info.evaluate();
+        while info.is_required_memory_slice() {
+            let mut slice = info.required_memory_slice();
+            requests.push((slice.address, slice.byte_size));
+
+            let (start, block) = memory
+                .iter()
+                .find(|(start, block)| {
+                    *start <= slice.address
+                        && slice.address + slice.byte_size as u64 <= start + block.len() as u64
+                })
+                .expect("the request is inside a block of memory");
+            let offset = (slice.address - start) as usize;
+            slice.set_memory_slice(&block[offset..offset + slice.byte_size]);
+            result = info.resume_with_memory_slice(slice);
+        }
+        (requests, result)
+    }
+
+    static NO_VALUES: WasmValueVector = WasmValueVector { data: Vec::new() };
+
+    /// Frame without any wasm locals, globals or operand stack
+    fn no_values() -> WasmFrameValues<'static> {
+        WasmFrameValues {
+            locals: &NO_VALUES,
+            globals: &NO_VALUES,
+            stacks: &NO_VALUES,
+        }
+    }
+
+    /// VariableInfo of the expression in a function of the unit at code_offset
+    fn evaluate_at(
+        dwarf_unit: &mut DwarfUnit,
+        expression: &str,
+        code_offset: u64,
     ) -> Result<Option<VariableInfo>> {
-        self.display_variable(unit_offset, FrameBase::WasmDataBase(data_base as u64), opts)
+        evaluate_with_values(dwarf_unit, expression, code_offset, no_values())
+    }
+
+    fn evaluate_with_values(
+        dwarf_unit: &mut DwarfUnit,
+        expression: &str,
+        code_offset: u64,
+        values: WasmFrameValues,
+    ) -> Result<Option<VariableInfo>> {
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&write_sections(dwarf_unit, byte_order), byte_order);
+        let unit = first_unit(&dwarf);
+        let variables = variables_in_unit_entry(&dwarf, &unit, None, 0, 0, false).unwrap();
+        evaluate_variable_from_string(
+            expression,
+            &variables,
+            &dwarf,
+            &unit,
+            FrameBase::WasmDataBase(0),
+            values,
+            code_offset,
+            &PrettyPrinters::default(),
+        )
+    }
+
+    /// Location list of DW_OP_addr expressions which are valid in the code ranges
+    fn address_list(ranges: &[(u64, u64, u32)]) -> write::LocationList {
+        write::LocationList(
+            ranges
+                .iter()
+                .map(|(begin, end, address)| {
+                    let mut expression = vec![gimli::DW_OP_addr.0];
+                    expression.extend_from_slice(&address.to_le_bytes());
+                    write::Location::StartEnd {
+                        begin: write::Address::Constant(*begin),
+                        end: write::Address::Constant(*end),
+                        data: write::Expression::raw(expression),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn variables_outside_their_location_list_report_the_code_offset() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let locations = unit.locations.add(address_list(&[(0x10, 0x20, 0x100)]));
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let counter = add_variable(unit, "counter", int, None);
+        unit.get_mut(counter).set(
+            gimli::DW_AT_location,
+            write::AttributeValue::LocationListRef(locations),
+        );
+
+        let mut info = evaluate_at(&mut dwarf_unit, "counter", 0x18)
+            .unwrap()
+            .unwrap();
+        assert_eq!(info.evaluate(), None);
+        assert_eq!(info.required_memory_slice().address, 0x100);
+        let error = evaluate_at(&mut dwarf_unit, "counter", 0x30).err().unwrap();
+        assert_eq!(error.to_string(), "'counter' has no location at 0x30");
+    }
+
+    #[test]
+    fn variables_in_wasm_locals_and_globals_are_read_from_their_values() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        // DW_OP_WASM_location local 1, then global 0
+        for (name, kind, index) in [("counter", 0x00, 1), ("limit", 0x01, 0)] {
+            let variable = add_variable(unit, name, int, None);
+            unit.get_mut(variable).set(
+                gimli::DW_AT_location,
+                write::AttributeValue::Exprloc(write::Expression::raw(vec![0xed, kind, index])),
+            );
+        }
+
+        let mut locals = WasmValueVector::new();
+        locals.push(WasmValue::from_i32(0));
+        locals.push(WasmValue::from_i32(-7));
+        let mut globals = WasmValueVector::new();
+        globals.push(WasmValue::from_i32(42));
+        let values = WasmFrameValues {
+            locals: &locals,
+            globals: &globals,
+            stacks: &NO_VALUES,
+        };
+
+        for (name, expected) in [("counter", "(int)-7"), ("limit", "(int)42")] {
+            let mut info = evaluate_with_values(&mut dwarf_unit, name, 0, values)
+                .unwrap()
+                .unwrap();
+            assert_eq!(info.evaluate(), Some(expected.to_string()));
+        }
+        let error = evaluate_with_values(&mut dwarf_unit, "limit", 0, no_values())
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "no value at wasm global 0");
+    }
+
+    /// Sections of a DWARF 5 unit whose variable x is located through .debug_loclists by
+    /// DW_FORM_loclistx and DW_AT_loclists_base, gimli can't write that form
+    fn loclistx_sections(lists: &[&[(u64, u64, u32)]]) -> HashMap<String, Vec<u8>> {
+        let mut abbrev = Vec::new();
+        for (code, tag, attributes) in [
+            (
+                1,
+                gimli::DW_TAG_compile_unit,
+                &[(gimli::DW_AT_loclists_base, gimli::DW_FORM_sec_offset)][..],
+            ),
+            (
+                2,
+                gimli::DW_TAG_base_type,
+                &[
+                    (gimli::DW_AT_name, gimli::DW_FORM_string),
+                    (gimli::DW_AT_encoding, gimli::DW_FORM_data1),
+                    (gimli::DW_AT_byte_size, gimli::DW_FORM_data1),
+                ],
+            ),
+            (
+                3,
+                gimli::DW_TAG_variable,
+                &[
+                    (gimli::DW_AT_name, gimli::DW_FORM_string),
+                    (gimli::DW_AT_type, gimli::DW_FORM_ref4),
+                    (gimli::DW_AT_location, gimli::DW_FORM_loclistx),
+                ],
+            ),
+        ] {
+            write_uleb128(&mut abbrev, code);
+            write_uleb128(&mut abbrev, tag.0 as u64);
+            abbrev.push((tag == gimli::DW_TAG_compile_unit) as u8);
+            for (name, form) in attributes {
+                write_uleb128(&mut abbrev, name.0 as u64);
+                write_uleb128(&mut abbrev, form.0 as u64);
+            }
+            abbrev.extend_from_slice(&[0, 0]);
+        }
+        abbrev.push(0);
+
+        // header: version 5, no segments, then an offset per list from the base
+        let header_size = 12;
+        let mut offsets = Vec::new();
+        let mut entries = Vec::new();
+        for list in lists {
+            offsets.extend_from_slice(&((lists.len() * 4 + entries.len()) as u32).to_le_bytes());
+            for (begin, end, address) in list.iter() {
+                entries.push(gimli::DW_LLE_offset_pair.0);
+                write_uleb128(&mut entries, *begin);
+                write_uleb128(&mut entries, *end);
+                write_uleb128(&mut entries, 5);
+                entries.push(gimli::DW_OP_addr.0);
+                entries.extend_from_slice(&address.to_le_bytes());
+            }
+            entries.push(gimli::DW_LLE_end_of_list.0);
+        }
+        let mut loclists = Vec::new();
+        let length = 8 + offsets.len() + entries.len();
+        loclists.extend_from_slice(&(length as u32).to_le_bytes());
+        loclists.extend_from_slice(&[5, 0, 4, 0]);
+        loclists.extend_from_slice(&(lists.len() as u32).to_le_bytes());
+        loclists.append(&mut offsets);
+        loclists.append(&mut entries);
+
+        // x refers to the last list, past the base type at offset 17 of the unit
+        let mut info = vec![0, 0, 0, 0, 5, 0, gimli::DW_UT_compile.0, 4, 0, 0, 0, 0];
+        info.push(1);
+        info.extend_from_slice(&(header_size as u32).to_le_bytes());
+        info.extend_from_slice(&[2, b'i', b'n', b't', 0, gimli::DW_ATE_signed.0, 4]);
+        info.extend_from_slice(&[3, b'x', 0]);
+        info.extend_from_slice(&17u32.to_le_bytes());
+        write_uleb128(&mut info, lists.len() as u64 - 1);
+        info.push(0);
+        let length = (info.len() - 4) as u32;
+        info[..4].copy_from_slice(&length.to_le_bytes());
+
+        let mut sections = HashMap::new();
+        sections.insert(".debug_abbrev".to_string(), abbrev);
+        sections.insert(".debug_info".to_string(), info);
+        sections.insert(".debug_loclists".to_string(), loclists);
+        sections
+    }
+
+    #[test]
+    fn loclistx_locations_are_found_through_the_loclists_base() {
+        let lists: &[&[(u64, u64, u32)]] = &[
+            &[(0x0, 0x8, 0x300)],
+            &[(0x10, 0x20, 0x100), (0x20, 0x30, 0x200)],
+        ];
+        let byte_order = RunTimeEndian::Little;
+        let dwarf = load_dwarf(&loclistx_sections(lists), byte_order);
+        let unit = first_unit(&dwarf);
+        let offset = find_entry(&unit, gimli::DW_TAG_variable).unwrap();
+        let location = unit
+            .entry(offset)
+            .unwrap()
+            .attr_value(gimli::DW_AT_location)
+            .unwrap()
+            .unwrap();
+        assert!(matches!(location, AttributeValue::DebugLocListsIndex(_)));
+
+        for (code_offset, address) in [
+            (0x10, Some(0x100u32)),
+            (0x2f, Some(0x200)),
+            // covered by the first list, which isn't the one of x
+            (0x4, None),
+            (0x30, None),
+        ] {
+            let expression =
+                location_list_expression(&dwarf, &unit, &location, code_offset).unwrap();
+            let expected = address.map(|address| {
+                let mut bytes = vec![gimli::DW_OP_addr.0];
+                bytes.extend_from_slice(&address.to_le_bytes());
+                bytes
+            });
+            assert_eq!(
+                expression.map(|expression| expression.0.to_slice().unwrap().to_vec()),
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn union_members_overlap_at_the_union_address() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let short = add_base_type(unit, "short", gimli::DW_ATE_signed, 2);
+        let value = add_composite(unit, gimli::DW_TAG_union_type, "Value", 4);
+        add_member(unit, value, "i", int, None);
+        add_member(unit, value, "s", short, None);
+        add_variable(unit, "v", value, Some(0x100));
+
+        let memory: &[(u64, &[u8])] = &[(0x100, &0x0001_0002i32.to_le_bytes())];
+        assert_eq!(
+            evaluate_with_memory(&mut dwarf_unit, "v.i", memory),
+            (vec![(0x100, 4)], Some("(int)65538".to_string()))
+        );
+        assert_eq!(
+            evaluate_with_memory(&mut dwarf_unit, "v.s", memory),
+            (vec![(0x100, 2)], Some("(short)2".to_string()))
+        );
+    }
+
+    #[test]
+    fn member_locations_in_constant_forms_are_byte_offsets() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        let record = add_composite(unit, gimli::DW_TAG_structure_type, "Record", 0x84);
+        add_member(unit, record, "a", int, None);
+        add_member(
+            unit,
+            record,
+            "b",
+            int,
+            Some(write::AttributeValue::Data2(4)),
+        );
+        add_member(
+            unit,
+            record,
+            "c",
+            int,
+            Some(write::AttributeValue::Sdata(8)),
+        );
+        // data forms are unsigned, 0x80 isn't -128
+        add_member(
+            unit,
+            record,
+            "d",
+            int,
+            Some(write::AttributeValue::Data1(0x80)),
+        );
+        add_variable(unit, "r", record, Some(0x100));
+
+        let memory: Vec<u8> = (0..0x84u32).flat_map(|i| (i * 10).to_le_bytes()).collect();
+        let memory: &[(u64, &[u8])] = &[(0x100, &memory)];
+        for (member, address, value) in [
+            ("a", 0x100, 0),
+            ("b", 0x104, 10),
+            ("c", 0x108, 20),
+            ("d", 0x180, 320),
+        ] {
+            assert_eq!(
+                evaluate_with_memory(&mut dwarf_unit, &format!("r.{}", member), memory),
+                (vec![(address, 4)], Some(format!("(int){}", value)))
+            );
+        }
+    }
+
+    #[test]
+    fn integers_of_128_bits_are_read_and_widened_whole() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+
+        let u128_type = add_base_type(unit, "u128", gimli::DW_ATE_unsigned, 16);
+        let i128_type = add_base_type(unit, "i128", gimli::DW_ATE_signed, 16);
+        add_variable(unit, "big", u128_type, Some(0x100));
+        let negative = add_variable(unit, "negative", i128_type, None);
+        unit.get_mut(negative)
+            .set(gimli::DW_AT_const_value, write::AttributeValue::Sdata(-5));
+
+        let big = (1u128 << 100).to_le_bytes();
+        assert_eq!(
+            evaluate_with_memory(&mut dwarf_unit, "big", &[(0x100, &big)]),
+            (
+                vec![(0x100, 16)],
+                Some("(u128)1267650600228229401496703205376".to_string())
+            )
+        );
+        assert_eq!(
+            evaluate_with_memory(&mut dwarf_unit, "negative", &[]),
+            (vec![], Some("(i128)-5".to_string()))
+        );
+    }
+
+    #[test]
+    fn void_pointers_format_as_addresses() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let root = unit.root();
+
+        let void_pointer = unit.add(root, gimli::DW_TAG_pointer_type);
+        unit.get_mut(void_pointer)
+            .set(gimli::DW_AT_byte_size, write::AttributeValue::Udata(4));
+        let holder = add_composite(unit, gimli::DW_TAG_structure_type, "Holder", 4);
+        add_member(unit, holder, "p", void_pointer, None);
+        add_variable(unit, "h", holder, Some(0x100));
+
+        assert_eq!(
+            evaluate_with_memory(&mut dwarf_unit, "h.p", &[(0x100, &0x1234u32.to_le_bytes())]),
+            (vec![(0x100, 4)], Some("(void *) 0x1234".to_string()))
+        );
+    }
+
+    #[test]
+    fn static_addresses_are_relocated_by_an_overridden_data_base() {
+        let mut dwarf_unit = DwarfUnit::new(encoding(4));
+        let unit = &mut dwarf_unit.unit;
+        let int = add_base_type(unit, "int", gimli::DW_ATE_signed, 4);
+        add_variable(unit, "counter", int, Some(0x10));
+
+        // without an override the address is used as linked
+        let memory: &[(u64, &[u8])] = &[(0x10, &5i32.to_le_bytes())];
+        assert_eq!(
+            evaluate_in_frame(
+                &mut dwarf_unit,
+                "counter",
+                FrameBase::WasmDataBase(0),
+                memory
+            ),
+            (vec![(0x10, 4)], Some("(int)5".to_string()))
+        );
+
+        // set_data_base(0x400)
+        let memory: &[(u64, &[u8])] = &[(0x410, &7i32.to_le_bytes())];
+        let expected = (vec![(0x410, 4)], Some("(int)7".to_string()));
+        assert_eq!(
+            evaluate_in_frame(
+                &mut dwarf_unit,
+                "counter",
+                FrameBase::WasmDataBase(0x400),
+                memory
+            ),
+            expected
+        );
+        // static locals, seen from a function with a frame base
+        let frame_base = FrameBase::WasmFrameBase {
+            frame_base: 0x9000,
+            data_base: 0x400,
+        };
+        assert_eq!(
+            evaluate_in_frame(&mut dwarf_unit, "counter", frame_base, memory),
+            expected
+        );
     }
 }
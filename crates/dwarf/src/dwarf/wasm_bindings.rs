@@ -1,7 +1,8 @@
 use super::sourcemap::{ColumnType, LineInfo};
+use super::subroutine::InlinedSubroutine;
 use super::variables::VariableName;
+use super::MemorySlice;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::*;
 
 #[derive(Clone, Copy, PartialEq, Debug)]
 pub enum Value {
@@ -9,6 +10,28 @@ pub enum Value {
     I64(i64),
     F32(f32),
     F64(f64),
+    V128([u8; 16]),
+}
+
+impl Value {
+    /// The value as stored in wasm memory
+    pub(crate) fn to_le_bytes(self) -> Vec<u8> {
+        match self {
+            Value::I32(v) => v.to_le_bytes().to_vec(),
+            Value::I64(v) => v.to_le_bytes().to_vec(),
+            Value::F32(v) => v.to_le_bytes().to_vec(),
+            Value::F64(v) => v.to_le_bytes().to_vec(),
+            Value::V128(v) => v.to_vec(),
+        }
+    }
+}
+
+/// Why the last lookup of DwarfDebugSymbolContainer returned nothing
+#[wasm_bindgen]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    NotFound,
+    EvaluationFailed,
 }
 
 #[wasm_bindgen]
@@ -41,6 +64,54 @@ impl WasmValue {
             value: Value::F64(v),
         }
     }
+
+    pub fn from_v128(bytes: &[u8]) -> Result<WasmValue, JsValue> {
+        if bytes.len() != 16 {
+            return Err(JsValue::from_str(&format!(
+                "v128 value requires 16 bytes, got {}",
+                bytes.len()
+            )));
+        }
+
+        let mut v = [0; 16];
+        v.copy_from_slice(bytes);
+        Ok(WasmValue {
+            value: Value::V128(v),
+        })
+    }
+
+    /// Interpret little-endian bytes as a value of the wasm type ("i32", "i64", "f32", "f64" or "v128")
+    pub fn from_bytes(kind: &str, bytes: &[u8]) -> Result<WasmValue, JsValue> {
+        let expected = match kind {
+            "i32" | "f32" => 4,
+            "i64" | "f64" => 8,
+            "v128" => 16,
+            _ => return Err(JsValue::from_str(&format!("unknown value type {}", kind))),
+        };
+        if bytes.len() != expected {
+            return Err(JsValue::from_str(&format!(
+                "{} value requires {} bytes, got {}",
+                kind,
+                expected,
+                bytes.len()
+            )));
+        }
+
+        let mut v = [0; 16];
+        v[..expected].copy_from_slice(bytes);
+        let value = match kind {
+            "i32" => Value::I32(i32::from_le_bytes([v[0], v[1], v[2], v[3]])),
+            "f32" => Value::F32(f32::from_le_bytes([v[0], v[1], v[2], v[3]])),
+            "i64" => Value::I64(i64::from_le_bytes([
+                v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7],
+            ])),
+            "f64" => Value::F64(f64::from_le_bytes([
+                v[0], v[1], v[2], v[3], v[4], v[5], v[6], v[7],
+            ])),
+            _ => Value::V128(v),
+        };
+        Ok(WasmValue { value })
+    }
 }
 
 #[wasm_bindgen]
@@ -64,6 +135,9 @@ pub struct WasmLineInfo {
     pub(crate) filepath: String,
     pub line: Option<usize>,
     pub column: Option<usize>,
+    pub is_stmt: bool,
+    /// The row starts at the beginning of the line, column is None then
+    pub left_edge: bool,
 }
 
 #[wasm_bindgen]
@@ -73,6 +147,8 @@ impl WasmLineInfo {
             filepath,
             line,
             column,
+            is_stmt: true,
+            left_edge: false,
         }
     }
 
@@ -88,6 +164,8 @@ impl WasmLineInfo {
                 ColumnType::Column(x) => Some(x as usize),
                 ColumnType::LeftEdge => None,
             },
+            is_stmt: info.is_stmt,
+            left_edge: info.column == ColumnType::LeftEdge,
         }
     }
 
@@ -99,10 +177,60 @@ impl WasmLineInfo {
                 Some(x) => ColumnType::Column(x as u64),
                 None => ColumnType::LeftEdge,
             },
+            is_stmt: info.is_stmt,
+        }
+    }
+}
+
+/// Source position and function name of an instruction, for call stacks
+#[wasm_bindgen]
+pub struct WasmFrameInfo {
+    pub(crate) filepath: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub(crate) function_name: Option<String>,
+}
+
+#[wasm_bindgen]
+impl WasmFrameInfo {
+    pub fn file(&self) -> String {
+        self.filepath.clone()
+    }
+
+    pub fn function_name(&self) -> Option<String> {
+        self.function_name.clone()
+    }
+
+    pub(crate) fn from_line_info(info: &LineInfo, function_name: Option<String>) -> Self {
+        let line_info = WasmLineInfo::from_line_info(info);
+        Self {
+            filepath: line_info.filepath,
+            line: line_info.line,
+            column: line_info.column,
+            function_name,
         }
     }
 }
 
+/// Function enclosing an address and the offset from the start of its range, for
+/// labels like func+0x12
+#[wasm_bindgen]
+pub struct WasmSymbolOffset {
+    pub(crate) name: String,
+    pub offset: usize,
+}
+
+#[wasm_bindgen]
+impl WasmSymbolOffset {
+    pub fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    pub fn label(&self) -> String {
+        format!("{}+0x{:x}", self.name, self.offset)
+    }
+}
+
 #[wasm_bindgen]
 pub struct VariableVector {
     data: Vec<VariableName>,
@@ -137,4 +265,145 @@ impl VariableVector {
     pub fn at_chile_group_id(&self, index: usize) -> Option<i32> {
         self.data[index].child_group_id
     }
+
+    pub fn at_die_offset(&self, index: usize) -> usize {
+        self.data[index].die_offset
+    }
+
+    /// Offset of the variable's type in its unit, for DwarfDebugSymbolContainer::describe_type
+    pub fn at_type_offset(&self, index: usize) -> Option<usize> {
+        self.data[index].type_offset
+    }
+
+    /// The variable is an argument of the function rather than a local
+    pub fn at_is_parameter(&self, index: usize) -> bool {
+        self.data[index].is_parameter
+    }
+
+    /// Number of lexical blocks around the variable, to tell shadowed names apart
+    pub fn at_scope_depth(&self, index: usize) -> u32 {
+        self.data[index].scope_depth
+    }
+
+    /// Source file declaring the variable, for "go to definition"
+    pub fn at_decl_file(&self, index: usize) -> Option<String> {
+        self.data[index].decl_file.clone()
+    }
+
+    pub fn at_decl_line(&self, index: usize) -> Option<usize> {
+        self.data[index].decl_line.map(|x| x as usize)
+    }
+
+    pub fn at_byte_size(&self, index: usize) -> usize {
+        self.data[index].byte_size
+    }
+
+    /// Short name of the DW_ATE encoding like "float" or "signed", "none" for composite types
+    pub fn at_encoding(&self, index: usize) -> String {
+        match self.data[index].encoding {
+            Some(encoding) => encoding
+                .static_string()
+                .map(|name| name.trim_start_matches("DW_ATE_").to_string())
+                .unwrap_or_else(|| format!("0x{:x}", encoding.0)),
+            None => String::from("none"),
+        }
+    }
+}
+
+#[wasm_bindgen]
+pub struct InlinedSubroutineVector {
+    data: Vec<InlinedSubroutine>,
+}
+
+#[wasm_bindgen]
+impl InlinedSubroutineVector {
+    pub(crate) fn from_vec(data: Vec<InlinedSubroutine>) -> Self {
+        Self { data }
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn at_name(&self, index: usize) -> Option<String> {
+        self.data[index].name.clone()
+    }
+
+    pub fn at_call_file(&self, index: usize) -> Option<String> {
+        self.data[index].call_file.clone()
+    }
+
+    pub fn at_call_line(&self, index: usize) -> Option<usize> {
+        self.data[index].call_line.map(|x| x as usize)
+    }
+}
+
+#[wasm_bindgen]
+pub struct StringVector {
+    data: Vec<String>,
+}
+
+#[wasm_bindgen]
+impl StringVector {
+    pub(crate) fn from_vec(data: Vec<String>) -> Self {
+        Self { data }
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn at(&self, index: usize) -> String {
+        self.data[index].clone()
+    }
+}
+
+#[wasm_bindgen]
+pub struct LineInfoVector {
+    data: Vec<Option<LineInfo>>,
+}
+
+#[wasm_bindgen]
+impl LineInfoVector {
+    pub(crate) fn from_vec(data: Vec<Option<LineInfo>>) -> Self {
+        Self { data }
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn at(&self, index: usize) -> Option<WasmLineInfo> {
+        self.data[index].as_ref().map(WasmLineInfo::from_line_info)
+    }
+}
+
+#[wasm_bindgen]
+pub struct MemorySliceVector {
+    pub(crate) data: Vec<MemorySlice>,
+}
+
+#[wasm_bindgen]
+impl MemorySliceVector {
+    pub fn new() -> MemorySliceVector {
+        MemorySliceVector { data: Vec::new() }
+    }
+
+    pub fn push(&mut self, slice: MemorySlice) {
+        self.data.push(slice);
+    }
+
+    pub fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn at(&self, index: usize) -> MemorySlice {
+        self.data[index].clone()
+    }
+}
+
+impl Default for MemorySliceVector {
+    fn default() -> Self {
+        Self::new()
+    }
 }
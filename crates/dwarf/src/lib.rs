@@ -1,40 +1,450 @@
 use anyhow::Result;
+use gimli::{DebugInfoOffset, UnitSectionOffset};
+use std::cell::RefCell;
 use wasm_bindgen::prelude::*;
-use wasm_bindgen::*;
 use wasmparser::{Parser, Payload};
 
 mod dwarf;
 
-use crate::dwarf::utils::error;
-use crate::dwarf::wasm_bindings::{VariableVector, WasmLineInfo, WasmValueVector};
-use crate::dwarf::{transform_dwarf, DwarfDebugInfo, VariableInfo};
+use crate::dwarf::pretty_printer::PrettyPrinters;
+use crate::dwarf::sourcemap::PathMatchMode;
+use crate::dwarf::utils::{demangle_name, error};
+use crate::dwarf::variables::{VariableName, VariableNotFound};
+use crate::dwarf::wasm_bindings::{
+    ErrorKind, InlinedSubroutineVector, LineInfoVector, StringVector, VariableVector,
+    WasmFrameInfo, WasmLineInfo, WasmSymbolOffset, WasmValueVector,
+};
+use crate::dwarf::{
+    transform_dwarf, DwarfDebugData, DwarfDebugInfo, DwarfDebugInfoParser, VariableInfo,
+};
 
 #[wasm_bindgen]
 pub struct DwarfDebugSymbolContainer {
     debug_info: DwarfDebugInfo,
     code_base: usize,
     data_base: usize,
+    demangle: bool,
+    pretty_printers: PrettyPrinters,
+    /// Reason of the last failed get_variable_info
+    last_error: RefCell<Option<(ErrorKind, String)>>,
+    /// Debug data with the split DWARF sections added until finish_supplementary
+    supplementary: Option<DwarfDebugData>,
+}
+
+/// Builds a DwarfDebugSymbolContainer one compilation unit at a time, so that JS can
+/// return to the event loop between parse_next_unit calls on large modules
+#[wasm_bindgen]
+pub struct DwarfDebugSymbolLoader {
+    parser: DwarfDebugInfoParser,
+    code_base: usize,
+    data_base: usize,
+}
+
+#[wasm_bindgen]
+impl DwarfDebugSymbolLoader {
+    pub fn new(data: &[u8]) -> Result<DwarfDebugSymbolLoader, JsValue> {
+        let base = calculate_code_base(data).ok().unwrap_or((0, 0));
+        let parser = DwarfDebugData::new(data)
+            .and_then(DwarfDebugInfoParser::new)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        Ok(DwarfDebugSymbolLoader {
+            parser,
+            code_base: base.0,
+            data_base: base.1,
+        })
+    }
+
+    pub fn unit_count(&self) -> usize {
+        self.parser.unit_count()
+    }
+
+    pub fn parsed_units(&self) -> usize {
+        self.parser.parsed_units()
+    }
+
+    /// Parse the next unit, returns whether units are left
+    pub fn parse_next_unit(&mut self) -> Result<bool, JsValue> {
+        self.parser
+            .parse_next_unit()
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Container of the units parsed so far, every unit once parse_next_unit returned false
+    pub fn finish(self) -> DwarfDebugSymbolContainer {
+        let mut debug_info = self.parser.finish();
+        debug_info.subroutine.data_base = self.data_base;
+
+        DwarfDebugSymbolContainer {
+            code_base: self.code_base,
+            data_base: self.data_base,
+            demangle: false,
+            pretty_printers: PrettyPrinters::default(),
+            last_error: RefCell::new(None),
+            supplementary: None,
+            debug_info,
+        }
+    }
 }
 
 #[wasm_bindgen]
 impl DwarfDebugSymbolContainer {
     pub fn new(data: &[u8]) -> Self {
         let base = calculate_code_base(data).ok().unwrap_or((0, 0));
+        let mut debug_info = transform_dwarf(data).unwrap();
+        debug_info.subroutine.data_base = base.1;
 
         DwarfDebugSymbolContainer {
             code_base: base.0,
             data_base: base.1,
-            debug_info: transform_dwarf(data).unwrap(),
+            demangle: false,
+            pretty_printers: PrettyPrinters::default(),
+            last_error: RefCell::new(None),
+            supplementary: None,
+            debug_info,
+        }
+    }
+
+    /// Same as new, calling progress(parsedUnits, totalUnits) while the units are parsed
+    /// so the host can report on large modules. The units are parsed before this returns,
+    /// DwarfDebugSymbolLoader lets the host yield between them
+    pub fn new_with_progress(
+        data: &[u8],
+        progress: &js_sys::Function,
+    ) -> Result<DwarfDebugSymbolContainer, JsValue> {
+        let mut loader = DwarfDebugSymbolLoader::new(data)?;
+        let total = loader.unit_count();
+        loop {
+            let _ = progress.call2(
+                &JsValue::NULL,
+                &JsValue::from(loader.parsed_units() as u32),
+                &JsValue::from(total as u32),
+            );
+            if loader.parsed_units() == total {
+                break;
+            }
+            loader.parse_next_unit()?;
+        }
+        Ok(loader.finish())
+    }
+
+    /// Load debug info from a separate symbol file, code and data bases from the stripped binary
+    pub fn from_split(
+        code_wasm: &[u8],
+        debug_wasm: &[u8],
+    ) -> Result<DwarfDebugSymbolContainer, JsValue> {
+        let base = calculate_code_base(code_wasm).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let debug_base =
+            calculate_code_base(debug_wasm).map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        // a symbol file without a code section can't be checked
+        if debug_base.0 != 0 && debug_base != base {
+            return Err(JsValue::from_str(&format!(
+                "symbol file offsets (code 0x{:x}, data 0x{:x}) don't match the binary (code 0x{:x}, data 0x{:x})",
+                debug_base.0, debug_base.1, base.0, base.1
+            )));
+        }
+
+        let mut debug_info =
+            transform_dwarf(debug_wasm).map_err(|e| JsValue::from_str(&e.to_string()))?;
+        debug_info.subroutine.data_base = base.1;
+
+        Ok(DwarfDebugSymbolContainer {
+            code_base: base.0,
+            data_base: base.1,
+            demangle: false,
+            pretty_printers: PrettyPrinters::default(),
+            last_error: RefCell::new(None),
+            supplementary: None,
+            debug_info,
+        })
+    }
+
+    /// Add a section of the split DWARF file (.dwo / .dwp), e.g. ".debug_info.dwo". The
+    /// sections are used once finish_supplementary is called after the last of them
+    pub fn add_supplementary_sections(&mut self, name: &str, data: &[u8]) -> Result<(), JsValue> {
+        let mut dwarf_data = self
+            .supplementary
+            .take()
+            .unwrap_or_else(|| self.debug_info.global_variables.dwarf_data.clone());
+        let result = dwarf_data.add_supplementary_sections(name, data);
+        self.supplementary = Some(dwarf_data);
+        result.map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    /// Parse the split units with the sections added by add_supplementary_sections
+    pub fn finish_supplementary(&mut self) -> Result<(), JsValue> {
+        match self.supplementary.take() {
+            Some(dwarf_data) => self
+                .debug_info
+                .load_supplementary(dwarf_data)
+                .map_err(|e| JsValue::from_str(&e.to_string())),
+            None => Ok(()),
         }
     }
 
+    /// Replace `from` with `to` in the source paths recorded in DWARF, e.g. to map
+    /// the build machine's directory to the local checkout
+    pub fn set_directory_map(&self, from: String, to: String) {
+        self.debug_info.sourcemap.set_directory_map(from, to);
+    }
+
+    /// Match breakpoint source paths exactly, or by the longest common path suffix
+    pub fn set_path_match_mode(&mut self, mode: PathMatchMode) {
+        self.debug_info.sourcemap.set_path_match_mode(mode);
+    }
+
+    /// Override the code section offset computed from the module
+    pub fn set_code_base(&mut self, code_base: usize) {
+        self.code_base = code_base;
+    }
+
+    /// Relocate the addresses of globals and static locals by data_base. DW_OP_addr of
+    /// linked modules is already a linear memory address, so the base is 0 unless set here
+    pub fn set_data_base(&mut self, data_base: usize) {
+        self.data_base = data_base;
+        self.debug_info.subroutine.data_base = data_base;
+    }
+
+    pub fn producer(&self) -> Option<String> {
+        self.debug_info.producer.clone()
+    }
+
+    pub fn dwarf_version(&self) -> Option<u16> {
+        self.debug_info.dwarf_version
+    }
+
+    pub fn unit_count(&self) -> usize {
+        self.debug_info.units.len()
+    }
+
+    /// JSON array of {name, comp_dir} of the parsed compilation units
+    pub fn unit_summary(&self) -> String {
+        serde_json::to_string(&self.debug_info.units).unwrap_or_default()
+    }
+
+    /// Source language of the unit containing the instruction, see UnitSummary::language
+    pub fn language_at_address(&self, instruction_offset: usize) -> Option<String> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        let subroutine = self
+            .debug_info
+            .subroutine
+            .find_subroutine(code_offset)
+            .ok()?;
+        self.debug_info
+            .units
+            .iter()
+            .find(|unit| unit.offset == subroutine.unit_offset)
+            .and_then(|unit| unit.language.clone())
+    }
+
     pub fn find_file_info_from_address(&self, instruction_offset: usize) -> Option<WasmLineInfo> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        self.debug_info
+            .sourcemap
+            .find_line_info(code_offset)
+            .map(|x| WasmLineInfo::from_line_info(&x))
+    }
+
+    /// [start, end) addresses of the line table row containing the instruction, e.g. for
+    /// running to a statement
+    pub fn find_line_range_from_address(&self, instruction_offset: usize) -> Option<Vec<usize>> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        let (start, end) = self.debug_info.sourcemap.find_line_range(code_offset)?;
+        Some(vec![start + self.code_base, end + self.code_base])
+    }
+
+    /// Cheap check whether the instruction is covered by the debug info, to skip
+    /// symbolication of host functions and glue code
+    pub fn has_debug_info_for(&self, instruction_offset: usize) -> bool {
+        let code_offset = match self.code_offset(instruction_offset) {
+            Some(x) => x,
+            None => return false,
+        };
+        self.debug_info.subroutine.contains(code_offset)
+            || self.debug_info.sourcemap.covers(code_offset)
+    }
+
+    /// find_file_info_from_address for each of the instructions, in one call
+    pub fn find_file_info_from_addresses(&self, instruction_offsets: &[usize]) -> LineInfoVector {
+        LineInfoVector::from_vec(
+            instruction_offsets
+                .iter()
+                .map(|offset| {
+                    self.code_offset(*offset)
+                        .and_then(|x| self.debug_info.sourcemap.find_line_info(x))
+                })
+                .collect(),
+        )
+    }
+
+    /// Source position of the statement containing the instruction, for line stepping
+    pub fn find_statement_info_from_address(
+        &self,
+        instruction_offset: usize,
+    ) -> Option<WasmLineInfo> {
+        let code_offset = self.code_offset(instruction_offset)?;
         self.debug_info
             .sourcemap
-            .find_line_info(instruction_offset - self.code_base)
+            .find_statement_line_info(code_offset)
             .map(|x| WasmLineInfo::from_line_info(&x))
     }
 
+    pub fn find_function_info_from_address(&self, instruction_offset: usize) -> Option<String> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        self.debug_info
+            .subroutine
+            .find_subroutine(code_offset)
+            .ok()
+            .and_then(|x| x.name.clone())
+            .map(|name| self.demangled(name))
+    }
+
+    /// Enclosing function and the offset into it, for annotating disassembly
+    pub fn symbol_offset_from_address(
+        &self,
+        instruction_offset: usize,
+    ) -> Option<WasmSymbolOffset> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        let subroutine = self
+            .debug_info
+            .subroutine
+            .find_subroutine(code_offset)
+            .ok()?;
+        let range = subroutine
+            .pc
+            .iter()
+            .find(|range| range.contains(&(code_offset as u64)))?;
+
+        Some(WasmSymbolOffset {
+            name: self.demangled(subroutine.name.clone()?),
+            offset: code_offset - range.start as usize,
+        })
+    }
+
+    /// PC ranges of the enclosing function, flattened as [low, high, low, high, ...]
+    pub fn function_range_from_address(&self, instruction_offset: usize) -> Option<Vec<usize>> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        let subroutine = self
+            .debug_info
+            .subroutine
+            .find_subroutine(code_offset)
+            .ok()?;
+
+        let mut ranges = subroutine.pc.clone();
+        ranges.sort_by_key(|range| range.start);
+
+        Some(
+            ranges
+                .into_iter()
+                .flat_map(|range| vec![range.start as usize, range.end as usize])
+                .map(|x| x + self.code_base)
+                .collect(),
+        )
+    }
+
+    /// Line table rows of the enclosing function, flattened as [address, line, address, line, ...]
+    pub fn function_line_rows_from_address(&self, instruction_offset: usize) -> Option<Vec<usize>> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        let subroutine = self
+            .debug_info
+            .subroutine
+            .find_subroutine(code_offset)
+            .ok()?;
+
+        Some(
+            self.debug_info
+                .sourcemap
+                .line_rows_in_ranges(&subroutine.pc)
+                .into_iter()
+                .flat_map(|(address, line)| vec![address + self.code_base, line as usize])
+                .collect(),
+        )
+    }
+
+    /// Names of every function whose range covers the instruction, innermost first
+    pub fn find_all_function_names_from_address(&self, instruction_offset: usize) -> StringVector {
+        let code_offset = match self.code_offset(instruction_offset) {
+            Some(x) => x,
+            None => return StringVector::from_vec(Vec::new()),
+        };
+        StringVector::from_vec(
+            self.debug_info
+                .subroutine
+                .find_subroutines(code_offset)
+                .into_iter()
+                .map(|x| {
+                    x.name
+                        .clone()
+                        .map(|name| self.demangled(name))
+                        .unwrap_or_else(|| String::from("<unknown>"))
+                })
+                .collect(),
+        )
+    }
+
+    /// find_file_info_from_address and find_function_info_from_address in one call
+    pub fn find_frame_info_from_address(&self, instruction_offset: usize) -> Option<WasmFrameInfo> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        let line_info = self.debug_info.sourcemap.find_line_info(code_offset)?;
+        let function_name = self
+            .debug_info
+            .subroutine
+            .find_subroutine(code_offset)
+            .ok()
+            .and_then(|x| x.name.clone())
+            .map(|name| self.demangled(name));
+
+        Some(WasmFrameInfo::from_line_info(&line_info, function_name))
+    }
+
+    pub fn find_inlined_functions_from_address(
+        &self,
+        instruction_offset: usize,
+    ) -> Option<InlinedSubroutineVector> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        match self.debug_info.subroutine.inlined_subroutines(code_offset) {
+            Ok(x) => Some(InlinedSubroutineVector::from_vec(
+                x.into_iter()
+                    .map(|mut x| {
+                        x.name = x.name.map(|name| self.demangled(name));
+                        x
+                    })
+                    .collect(),
+            )),
+            Err(e) => {
+                console_log!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Locals past the arguments to the frame pointer, for functions without DW_AT_frame_base
+    pub fn set_rbp_local_offset(&mut self, offset: usize) {
+        self.debug_info.subroutine.rbp_local_offset = offset;
+    }
+
+    pub fn set_demangle(&mut self, enabled: bool) {
+        self.demangle = enabled;
+    }
+
+    /// Render std::string and Rust String / &str as their text
+    pub fn set_pretty_print_strings(&mut self, enabled: bool) {
+        self.pretty_printers.set_builtin_strings(enabled);
+    }
+
+    /// Render structs whose type name matches type_pattern with the JSON spec, e.g.
+    /// `{"kind": "pointer_length_string", "data": "__data", "length": "__size"}`
+    pub fn register_pretty_printer(
+        &mut self,
+        type_pattern: &str,
+        spec_json: &str,
+    ) -> Result<(), JsValue> {
+        self.pretty_printers
+            .register(type_pattern, spec_json)
+            .map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
     pub fn find_address_from_file_info(&self, info: &WasmLineInfo) -> Option<usize> {
         let file_info = WasmLineInfo::into_line_info(info);
         self.debug_info
@@ -43,13 +453,49 @@ impl DwarfDebugSymbolContainer {
             .map(|x| x + self.code_base)
     }
 
+    /// [line, address] a breakpoint on the line binds to, for moving the editor's marker
+    pub fn resolve_breakpoint_line(&self, info: &WasmLineInfo) -> Option<Vec<usize>> {
+        let file_info = WasmLineInfo::into_line_info(info);
+        let (line, address) = self
+            .debug_info
+            .sourcemap
+            .resolve_breakpoint_line(&file_info)?;
+        Some(vec![line as usize, address + self.code_base])
+    }
+
+    pub fn find_all_addresses_from_file_info(&self, info: &WasmLineInfo) -> Vec<usize> {
+        let file_info = WasmLineInfo::into_line_info(info);
+        self.debug_info
+            .sourcemap
+            .find_all_addresses(&file_info)
+            .into_iter()
+            .map(|x| x + self.code_base)
+            .collect()
+    }
+
+    /// Like find_all_addresses_from_file_info, skipping rows that aren't statement boundaries
+    pub fn find_statement_addresses_from_file_info(&self, info: &WasmLineInfo) -> Vec<usize> {
+        let file_info = WasmLineInfo::into_line_info(info);
+        self.debug_info
+            .sourcemap
+            .find_statement_addresses(&file_info)
+            .into_iter()
+            .map(|x| x + self.code_base)
+            .collect()
+    }
+
+    pub fn source_file_list(&self) -> StringVector {
+        StringVector::from_vec(self.debug_info.sourcemap.source_files())
+    }
+
     pub fn variable_name_list(&self, instruction_offset: usize) -> Option<VariableVector> {
+        let code_offset = self.code_offset(instruction_offset)?;
         match self
             .debug_info
             .subroutine
-            .variable_name_list(instruction_offset - self.code_base, 1000)
+            .variable_name_list(code_offset, 1000)
         {
-            Ok(x) => Some(VariableVector::from_vec(x)),
+            Ok(x) => Some(VariableVector::from_vec(self.demangled_variable_names(x))),
             Err(e) => {
                 console_log!("{}", e);
                 None
@@ -57,12 +503,167 @@ impl DwarfDebugSymbolContainer {
         }
     }
 
+    /// Number of variables listed by variable_name_list, e.g. for a lazily paged scope
+    pub fn variable_count(&self, instruction_offset: usize) -> usize {
+        let code_offset = match self.code_offset(instruction_offset) {
+            Some(x) => x,
+            None => return 0,
+        };
+        match self.debug_info.subroutine.variable_count(code_offset) {
+            Ok(x) => x,
+            Err(e) => {
+                console_log!("{}", e);
+                0
+            }
+        }
+    }
+
     pub fn global_variable_name_list(&self, instruction: usize) -> Option<VariableVector> {
-        let subroutine = match self
+        let code_offset = self.code_offset(instruction)?;
+        let subroutine = match self.debug_info.subroutine.find_subroutine(code_offset) {
+            Ok(x) => x,
+            Err(e) => {
+                console_log!("{}", e);
+                return None;
+            }
+        };
+
+        self.global_variable_names(subroutine.unit_offset)
+    }
+
+    /// Offsets of all units in .debug_info, to inspect globals outside of any known function
+    pub fn unit_offset_list(&self) -> Vec<usize> {
+        match self.debug_info.global_variables.dwarf_data.unit_offsets() {
+            Ok(x) => x
+                .into_iter()
+                .filter_map(|offset| match offset {
+                    UnitSectionOffset::DebugInfoOffset(offset) => Some(offset.0),
+                    UnitSectionOffset::DebugTypesOffset(_) => None,
+                })
+                .collect(),
+            Err(e) => {
+                console_log!("{}", e);
+                Vec::new()
+            }
+        }
+    }
+
+    /// Limit the entries of a variable list or an expanded group, the rest is listed
+    /// by expanding the last entry. 0 removes the limit
+    pub fn set_max_variables(&mut self, max_variables: usize) {
+        let max_variables = match max_variables {
+            0 => None,
+            n => Some(n),
+        };
+        self.debug_info
+            .subroutine
+            .lazy_groups
+            .get_mut()
+            .max_variables = max_variables;
+        self.debug_info
+            .global_variables
+            .lazy_groups
+            .get_mut()
+            .max_variables = max_variables;
+    }
+
+    /// Limit how deep groups nest below a variable list, members nested deeper are
+    /// listed by expanding the entry standing in for them. 0 removes the limit
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        let max_depth = match max_depth {
+            0 => None,
+            n => Some(n),
+        };
+        self.debug_info.subroutine.lazy_groups.get_mut().max_depth = max_depth;
+        self.debug_info
+            .global_variables
+            .lazy_groups
+            .get_mut()
+            .max_depth = max_depth;
+    }
+
+    /// Text dump of the DIE tree of a unit, in the order of unit_summary
+    pub fn dump_die_tree(&self, unit_index: usize) -> String {
+        match self
+            .debug_info
+            .global_variables
+            .dwarf_data
+            .dump_die_tree(unit_index)
+        {
+            Ok(Some(x)) => x,
+            Ok(None) => format!("unit {} not found", unit_index),
+            Err(e) => format!("failed to dump unit {}: {}", unit_index, e),
+        }
+    }
+
+    /// Same as global_variable_name_list for the unit at unit_offset in .debug_info
+    pub fn global_variable_name_list_in_unit(&self, unit_offset: usize) -> Option<VariableVector> {
+        self.global_variable_names(UnitSectionOffset::DebugInfoOffset(DebugInfoOffset(
+            unit_offset,
+        )))
+    }
+
+    /// Members of a struct listed by global_variable_name_list_in_unit
+    pub fn expand_global_group_in_unit(
+        &self,
+        unit_offset: usize,
+        group_id: i32,
+    ) -> Option<VariableVector> {
+        match self.debug_info.global_variables.expand_group(
+            UnitSectionOffset::DebugInfoOffset(DebugInfoOffset(unit_offset)),
+            group_id,
+        ) {
+            Ok(x) => x.map(|x| VariableVector::from_vec(self.demangled_variable_names(x))),
+            Err(e) => {
+                console_log!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// Evaluate a global of the unit at unit_offset, the instruction may lie in another unit
+    /// but still selects the entry of a location list
+    pub fn get_global_variable_info_in_unit(
+        &self,
+        opts: String,
+        unit_offset: usize,
+        globals: &WasmValueVector,
+        instruction_offset: usize,
+    ) -> Option<VariableInfo> {
+        *self.last_error.borrow_mut() = None;
+        let code_offset = self.checked_code_offset(instruction_offset)?;
+        match self.debug_info.global_variables.get_variable_info(
+            &opts,
+            UnitSectionOffset::DebugInfoOffset(DebugInfoOffset(unit_offset)),
+            self.data_base,
+            globals,
+            code_offset,
+            &self.pretty_printers,
+        ) {
+            Ok(x) => x,
+            Err(e) => {
+                console_log!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// List the members of a struct variable listed by (global_)variable_name_list
+    pub fn expand_group(&self, instruction_offset: usize, group_id: i32) -> Option<VariableVector> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        match self
             .debug_info
             .subroutine
-            .find_subroutine(instruction - self.code_base)
+            .expand_group(code_offset, group_id)
         {
+            Ok(Some(x)) => return Some(VariableVector::from_vec(self.demangled_variable_names(x))),
+            Ok(None) => {}
+            Err(e) => {
+                console_log!("{}", e);
+            }
+        };
+
+        let subroutine = match self.debug_info.subroutine.find_subroutine(code_offset) {
             Ok(x) => x,
             Err(e) => {
                 console_log!("{}", e);
@@ -73,9 +674,9 @@ impl DwarfDebugSymbolContainer {
         match self
             .debug_info
             .global_variables
-            .variable_name_list(subroutine.unit_offset, 1001)
+            .expand_group(subroutine.unit_offset, group_id)
         {
-            Ok(x) => Some(VariableVector::from_vec(x)),
+            Ok(x) => x.map(|x| VariableVector::from_vec(self.demangled_variable_names(x))),
             Err(e) => {
                 console_log!("{}", e);
                 None
@@ -83,6 +684,36 @@ impl DwarfDebugSymbolContainer {
         }
     }
 
+    /// Offset of the unit containing the instruction in .debug_info, for describe_type
+    pub fn unit_offset_from_address(&self, instruction_offset: usize) -> Option<usize> {
+        let code_offset = self.code_offset(instruction_offset)?;
+        let subroutine = self
+            .debug_info
+            .subroutine
+            .find_subroutine(code_offset)
+            .ok()?;
+
+        match subroutine.unit_offset {
+            UnitSectionOffset::DebugInfoOffset(offset) => Some(offset.0),
+            UnitSectionOffset::DebugTypesOffset(_) => None,
+        }
+    }
+
+    /// Describe the type at type_offset as JSON, {"type_name", "byte_size", "members":
+    /// [{"name", "offset", "byte_size", "type_name"}]}
+    pub fn describe_type(&self, unit_offset: usize, type_offset: usize) -> Result<String, JsValue> {
+        let unit_offset = UnitSectionOffset::DebugInfoOffset(DebugInfoOffset(unit_offset));
+        match self
+            .debug_info
+            .global_variables
+            .describe_type(unit_offset, type_offset)
+        {
+            Ok(Some(x)) => Ok(x),
+            Ok(None) => Err(JsValue::from_str("unit not found")),
+            Err(e) => Err(JsValue::from_str(&e.to_string())),
+        }
+    }
+
     pub fn get_variable_info(
         &self,
         opts: String,
@@ -91,45 +722,167 @@ impl DwarfDebugSymbolContainer {
         stacks: &WasmValueVector,
         instruction_offset: usize,
     ) -> Option<VariableInfo> {
-        match self.debug_info.subroutine.get_variable_info(
+        *self.last_error.borrow_mut() = None;
+        let code_offset = self.checked_code_offset(instruction_offset)?;
+        let local = self.debug_info.subroutine.get_variable_info(
             &opts,
             locals,
             globals,
             stacks,
-            instruction_offset - self.code_base,
-        ) {
+            code_offset,
+            &self.pretty_printers,
+        );
+        self.local_or_global_variable_info(&opts, local, globals, code_offset)
+    }
+
+    /// Same as get_variable_info with the frame base given by the caller instead of read
+    /// from the locals, e.g. for frames of a recorded stack
+    pub fn get_variable_info_with_frame_base(
+        &self,
+        opts: String,
+        frame_base: u64,
+        instruction_offset: usize,
+    ) -> Option<VariableInfo> {
+        *self.last_error.borrow_mut() = None;
+        let code_offset = self.checked_code_offset(instruction_offset)?;
+        let local = self
+            .debug_info
+            .subroutine
+            .get_variable_info_with_frame_base(
+                &opts,
+                frame_base,
+                code_offset,
+                &self.pretty_printers,
+            );
+        self.local_or_global_variable_info(&opts, local, &WasmValueVector::new(), code_offset)
+    }
+
+    /// Reason of the last get_variable_info which returned nothing
+    pub fn last_error(&self) -> Option<String> {
+        self.last_error
+            .borrow()
+            .as_ref()
+            .map(|(_, message)| message.clone())
+    }
+
+    pub fn last_error_kind(&self) -> Option<ErrorKind> {
+        self.last_error.borrow().as_ref().map(|(kind, _)| *kind)
+    }
+}
+
+impl DwarfDebugSymbolContainer {
+    fn set_error(&self, kind: ErrorKind, message: String) {
+        *self.last_error.borrow_mut() = Some((kind, message));
+    }
+
+    fn checked_code_offset(&self, instruction_offset: usize) -> Option<usize> {
+        let code_offset = self.code_offset(instruction_offset);
+        if code_offset.is_none() {
+            self.set_error(
+                ErrorKind::EvaluationFailed,
+                format!("0x{:x} precedes the code section", instruction_offset),
+            );
+        }
+        code_offset
+    }
+
+    /// Fall back to the globals of the unit when the local lookup found nothing
+    fn local_or_global_variable_info(
+        &self,
+        opts: &String,
+        local: anyhow::Result<Option<VariableInfo>>,
+        globals: &WasmValueVector,
+        code_offset: usize,
+    ) -> Option<VariableInfo> {
+        // a local which can't be evaluated is reported unless a global of the name exists
+        let mut local_error = None;
+        match local {
             Ok(Some(x)) => return Some(x),
-            Ok(None) => {}
+            Ok(None) => local_error = Some(format!("'{}' has no value here", opts)),
             Err(e) => {
-                console_log!("{}", e)
+                console_log!("{}", e);
+                if !e.is::<VariableNotFound>() {
+                    local_error = Some(e.to_string());
+                }
             }
         };
 
-        let subroutine = match self
-            .debug_info
-            .subroutine
-            .find_subroutine(instruction_offset - self.code_base)
-        {
+        let subroutine = match self.debug_info.subroutine.find_subroutine(code_offset) {
             Ok(x) => x,
             Err(e) => {
                 console_log!("{}", e);
+                self.set_error(ErrorKind::EvaluationFailed, e.to_string());
                 return None;
             }
         };
 
         match self.debug_info.global_variables.get_variable_info(
-            &opts,
+            opts,
             subroutine.unit_offset,
             self.data_base,
             globals,
+            code_offset,
+            &self.pretty_printers,
         ) {
-            Ok(x) => x,
+            Ok(Some(x)) => Some(x),
+            Ok(None) => {
+                let message =
+                    local_error.unwrap_or_else(|| format!("'{}' has no value here", opts));
+                self.set_error(ErrorKind::EvaluationFailed, message);
+                None
+            }
             Err(e) => {
                 console_log!("{}", e);
+                match local_error {
+                    Some(message) if e.is::<VariableNotFound>() => {
+                        self.set_error(ErrorKind::EvaluationFailed, message)
+                    }
+                    _ if e.is::<VariableNotFound>() => {
+                        self.set_error(ErrorKind::NotFound, e.to_string())
+                    }
+                    _ => self.set_error(ErrorKind::EvaluationFailed, e.to_string()),
+                }
                 None
             }
         }
     }
+
+    /// Offset in the code section, None for instructions before it (e.g. in the function table)
+    fn code_offset(&self, instruction_offset: usize) -> Option<usize> {
+        instruction_offset.checked_sub(self.code_base)
+    }
+
+    fn global_variable_names(&self, unit_offset: UnitSectionOffset) -> Option<VariableVector> {
+        match self
+            .debug_info
+            .global_variables
+            .variable_name_list(unit_offset, 1001)
+        {
+            Ok(x) => Some(VariableVector::from_vec(self.demangled_variable_names(x))),
+            Err(e) => {
+                console_log!("{}", e);
+                None
+            }
+        }
+    }
+
+    fn demangled(&self, name: String) -> String {
+        if self.demangle {
+            demangle_name(&name)
+        } else {
+            name
+        }
+    }
+
+    fn demangled_variable_names(&self, names: Vec<VariableName>) -> Vec<VariableName> {
+        names
+            .into_iter()
+            .map(|mut x| {
+                x.type_name = self.demangled(std::mem::take(&mut x.type_name));
+                x
+            })
+            .collect()
+    }
 }
 
 fn calculate_code_base(data: &[u8]) -> Result<(usize, usize)> {
@@ -162,3 +915,84 @@ fn calculate_code_base(data: &[u8]) -> Result<(usize, usize)> {
     }
     Ok((code_section_offset, data_section_offset))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dwarf::test_utils::{line_table, push_data_segment, wasm_module};
+
+    #[test]
+    fn instruction_offsets_before_the_code_section_have_no_line_info() {
+        let sections = line_table(4, "main.c", &[(0x10, &[(0, 1, 0)], 0x4)]);
+        let container = DwarfDebugSymbolContainer::new(&wasm_module(&sections));
+        assert!(container.code_base > 0);
+
+        assert!(container.find_file_info_from_address(0).is_none());
+        assert!(container.find_line_range_from_address(0).is_none());
+        assert!(!container.has_debug_info_for(0));
+
+        let info = container
+            .find_file_info_from_address(container.code_base + 0x10)
+            .unwrap();
+        assert_eq!(info.line, Some(1));
+    }
+
+    #[test]
+    fn data_base_is_only_taken_from_the_override() {
+        let sections = line_table(4, "main.c", &[(0x10, &[(0, 1, 0)], 0x4)]);
+        let mut module = wasm_module(&sections);
+        push_data_segment(&mut module, 0x20);
+
+        // DW_OP_addr of a linked module already points into linear memory
+        let mut container = DwarfDebugSymbolContainer::new(&module);
+        assert_eq!(container.data_base, 0);
+        assert_eq!(container.debug_info.subroutine.data_base, 0);
+
+        container.set_data_base(0x400);
+        assert_eq!(container.data_base, 0x400);
+        assert_eq!(container.debug_info.subroutine.data_base, 0x400);
+    }
+
+    #[test]
+    fn supplementary_sections_are_parsed_once_finished() {
+        let sections = line_table(4, "main.c", &[(0x10, &[(0, 1, 0)], 0x4)]);
+        let mut container = DwarfDebugSymbolContainer::new(&wasm_module(&sections));
+        container.set_data_base(0x400);
+        container.set_rbp_local_offset(3);
+        container.set_max_variables(8);
+
+        for name in [".debug_info.dwo", ".debug_abbrev.dwo"] {
+            container.add_supplementary_sections(name, &[]).unwrap();
+        }
+        assert!(container.supplementary.is_some());
+        container.finish_supplementary().unwrap();
+        assert!(container.supplementary.is_none());
+
+        // the settings outlive the reparsed units
+        let subroutine = &mut container.debug_info.subroutine;
+        assert_eq!(subroutine.data_base, 0x400);
+        assert_eq!(subroutine.rbp_local_offset, 3);
+        assert_eq!(subroutine.lazy_groups.get_mut().max_variables, Some(8));
+        let info = container
+            .find_file_info_from_address(container.code_base + 0x10)
+            .unwrap();
+        assert_eq!(info.line, Some(1));
+    }
+
+    #[test]
+    fn loaders_parse_a_unit_per_call() {
+        let sections = line_table(4, "main.c", &[(0x10, &[(0, 1, 0)], 0x4)]);
+        let mut loader = DwarfDebugSymbolLoader::new(&wasm_module(&sections)).unwrap();
+        assert_eq!((loader.parsed_units(), loader.unit_count()), (0, 1));
+
+        assert!(!loader.parse_next_unit().unwrap());
+        assert_eq!(loader.parsed_units(), 1);
+        assert!(!loader.parse_next_unit().unwrap());
+
+        let container = loader.finish();
+        let info = container
+            .find_file_info_from_address(container.code_base + 0x10)
+            .unwrap();
+        assert_eq!(info.line, Some(1));
+    }
+}
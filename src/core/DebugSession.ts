@@ -64,6 +64,18 @@ export class DebugSession {
         return undefined;
     }
 
+    expandVariableGroup(address: number, groupId: number) {
+        for (const x of this.sources) {
+            const list = x.dwarf.expand_group(address, groupId);
+
+            if (list) {
+                return list;
+            }
+        }
+
+        return undefined;
+    }
+
     getGlobalVariablelist(inst: number) {
         const list = [];
 
@@ -108,7 +108,9 @@ export class PausedDebugSessionState implements DebuggerWorkflowCommand, Debugge
 
     async listVariable(variableReference?: number) {
         const frame = this.stackFrames[this.selectedFrameIndex];
-        const varlist = this.debugSession.getVariablelistFromAddress(frame.stack.instruction!);
+        const varlist = (variableReference && variableReference != 1000)
+            ? this.debugSession.expandVariableGroup(frame.stack.instruction!, variableReference)
+            : this.debugSession.getVariablelistFromAddress(frame.stack.instruction!);
 
         if (!varlist) {
             return [];
@@ -143,7 +145,9 @@ export class PausedDebugSessionState implements DebuggerWorkflowCommand, Debugge
 
     async listGlobalVariable(variableReference?: number) {
         const frame = this.stackFrames[this.selectedFrameIndex];
-        const varlists = this.debugSession.getGlobalVariablelist(frame.stack.instruction!);
+        const varlists = (variableReference && variableReference != 1001)
+            ? [ this.debugSession.expandVariableGroup(frame.stack.instruction!, variableReference) ]
+            : this.debugSession.getGlobalVariablelist(frame.stack.instruction!);
 
         if (varlists.length <= 0) {
             return [];
@@ -204,7 +208,7 @@ export class PausedDebugSessionState implements DebuggerWorkflowCommand, Debugge
 
         while (wasmVariable.is_required_memory_slice() && limit < 20) { 
             const slice = wasmVariable.required_memory_slice();
-            const result = await this.memoryEvaluator.evaluate(frame.frame.callFrameId, slice.address, slice.byte_size);
+            const result = await this.memoryEvaluator.evaluate(frame.frame.callFrameId, Number(slice.address), slice.byte_size);
             slice.set_memory_slice(new Uint8Array(result));
             evaluationResult = wasmVariable.resume_with_memory_slice(slice) || evaluationResult;
 